@@ -0,0 +1,241 @@
+use crate::{
+    DlssError, DlssTexture,
+    compute_pass::{
+        ScratchTexture, UniformValue, create_compute_pipeline, create_shader_module,
+        create_uniform_buffer, dispatch, storage_buffer_entry, storage_texture_entry,
+        texture_entry, uniform_entry,
+    },
+};
+use glam::UVec2;
+use std::{cmp::Ordering, time::Duration};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindingResource, Buffer, BufferDescriptor, BufferUsages, CommandEncoder, ComputePipeline,
+    Device, TextureFormat, TextureSampleType, TextureUsages,
+};
+
+/// Must match `BIN_COUNT` in `auto_exposure.wgsl`.
+const HISTOGRAM_BIN_COUNT: u64 = 64;
+
+/// Settings for [`DlssAutoExposurePass`].
+///
+/// EV values are base-2 logarithms of scene luminance, i.e. 0 EV is a luminance of 1.0.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DlssAutoExposureSettings {
+    /// Darkest scene luminance to adapt to.
+    pub min_ev: f32,
+    /// Brightest scene luminance to adapt to. Must be greater than `min_ev`.
+    pub max_ev: f32,
+    /// Fraction of the darkest pixels to ignore, between 0.0 and 1.0.
+    pub low_percentile: f32,
+    /// Fraction of pixels to include before ignoring the brightest pixels, between 0.0 and 1.0.
+    pub high_percentile: f32,
+    /// How fast to adapt when the scene gets brighter, in EV per second.
+    pub speed_brighten: f32,
+    /// How fast to adapt when the scene gets darker, in EV per second.
+    pub speed_darken: f32,
+    /// Offset applied to the final exposure, in EV. Positive values brighten the image.
+    pub compensation: f32,
+    /// How pixels are weighted based on their position on screen.
+    pub metering: DlssExposureMetering,
+}
+
+impl Default for DlssAutoExposureSettings {
+    fn default() -> Self {
+        Self {
+            min_ev: -8.0,
+            max_ev: 8.0,
+            low_percentile: 0.1,
+            high_percentile: 0.9,
+            speed_brighten: 3.0,
+            speed_darken: 1.0,
+            compensation: 0.0,
+            metering: DlssExposureMetering::default(),
+        }
+    }
+}
+
+/// How [`DlssAutoExposurePass`] weights pixels based on their position on screen.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum DlssExposureMetering {
+    /// Every pixel is weighted equally.
+    #[default]
+    Average,
+    /// Pixels near the center of the screen are weighted more than those near the edges.
+    CenterWeighted,
+    /// Only pixels within `radius` of the center of the screen are used, where 0.5 is half the screen.
+    Spot { radius: f32 },
+}
+
+/// Compute pass that measures a color texture's luminance histogram, and adapts exposure over time.
+///
+/// Produces an exposure texture for use with [`crate::DlssExposure::Manual`], so that DLSS and the application's
+/// tonemapper can share the same exposure without [`crate::DlssFeatureFlags::AutoExposure`].
+pub struct DlssAutoExposurePass {
+    histogram_pipeline: ComputePipeline,
+    exposure_pipeline: ComputePipeline,
+    bind_group_layout: BindGroupLayout,
+    histogram: Buffer,
+    adapted_ev: Buffer,
+    output: ScratchTexture,
+    reset: bool,
+}
+
+impl DlssAutoExposurePass {
+    pub fn new(device: &Device) -> Self {
+        let shader_module = create_shader_module(
+            device,
+            "dlss_auto_exposure",
+            include_str!("auto_exposure.wgsl"),
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("dlss_auto_exposure"),
+            entries: &[
+                uniform_entry(0),
+                texture_entry(1, TextureSampleType::Float { filterable: false }),
+                storage_buffer_entry(2),
+                storage_buffer_entry(3),
+                storage_texture_entry(4, TextureFormat::R32Float),
+            ],
+        });
+
+        Self {
+            histogram_pipeline: create_compute_pipeline(
+                device,
+                "dlss_auto_exposure_histogram",
+                &shader_module,
+                "compute_histogram",
+                &bind_group_layout,
+            ),
+            exposure_pipeline: create_compute_pipeline(
+                device,
+                "dlss_auto_exposure",
+                &shader_module,
+                "compute_exposure",
+                &bind_group_layout,
+            ),
+            bind_group_layout,
+            histogram: device.create_buffer(&BufferDescriptor {
+                label: Some("dlss_auto_exposure_histogram"),
+                size: HISTOGRAM_BIN_COUNT * 4,
+                usage: BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            }),
+            adapted_ev: device.create_buffer(&BufferDescriptor {
+                label: Some("dlss_auto_exposure_adapted_ev"),
+                size: 4,
+                usage: BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            }),
+            output: ScratchTexture::new(
+                "dlss_auto_exposure_output",
+                TextureFormat::R32Float,
+                TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            ),
+            reset: true,
+        }
+    }
+
+    /// Encode commands to measure `color` and update the exposure texture owned by this pass.
+    ///
+    /// `color` should be the linear HDR color before exposure is applied, e.g. [`crate::DlssRenderParameters::color`].
+    /// `delta_time` is the time since the previous frame, used for adaptation.
+    ///
+    /// The returned 1x1 `R32Float` texture contains the exposure multiplier, and can be used as
+    /// [`crate::DlssExposure::Manual`]'s `exposure`, or bound by the application's post-processing.
+    ///
+    /// Returns [`DlssError::InvalidRenderParameters`] if `settings.min_ev` is not less than `settings.max_ev`.
+    pub fn encode(
+        &mut self,
+        color: &DlssTexture,
+        settings: &DlssAutoExposureSettings,
+        delta_time: Duration,
+        command_encoder: &mut CommandEncoder,
+        device: &Device,
+    ) -> Result<DlssTexture<'_>, DlssError> {
+        if settings.min_ev.partial_cmp(&settings.max_ev) != Some(Ordering::Less) {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "DlssAutoExposureSettings has min_ev {}, but must be less than max_ev {}",
+                settings.min_ev, settings.max_ev
+            )));
+        }
+
+        self.output.resize(UVec2::ONE, device);
+        let output = self.output.texture();
+
+        let delta_time = delta_time.as_secs_f32();
+        let (metering, spot_radius) = match settings.metering {
+            DlssExposureMetering::Average => (0u32, 0.0),
+            DlssExposureMetering::CenterWeighted => (1, 0.0),
+            DlssExposureMetering::Spot { radius } => (2, radius),
+        };
+        let uniforms = [
+            settings.min_ev.uniform_words(),
+            settings.max_ev.uniform_words(),
+            settings.low_percentile.uniform_words(),
+            settings.high_percentile.uniform_words(),
+            (settings.speed_brighten * delta_time).uniform_words(),
+            (settings.speed_darken * delta_time).uniform_words(),
+            settings.compensation.uniform_words(),
+            spot_radius.uniform_words(),
+            metering.uniform_words(),
+            (self.reset as u32).uniform_words(),
+        ]
+        .concat();
+        let uniform_buffer = create_uniform_buffer(device, "dlss_auto_exposure", &uniforms);
+        self.reset = false;
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("dlss_auto_exposure"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(color.view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: self.histogram.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: self.adapted_ev.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::TextureView(output.view),
+                },
+            ],
+        });
+
+        dispatch(
+            command_encoder,
+            "dlss_auto_exposure_histogram",
+            &self.histogram_pipeline,
+            &bind_group,
+            color.size(),
+        );
+        // A single workgroup, one thread per histogram bin
+        dispatch(
+            command_encoder,
+            "dlss_auto_exposure",
+            &self.exposure_pipeline,
+            &bind_group,
+            UVec2::ONE,
+        );
+
+        Ok(output)
+    }
+
+    /// Snap to the measured exposure on the next [`Self::encode`], instead of adapting over time.
+    ///
+    /// Useful after camera cuts.
+    pub fn reset(&mut self) {
+        self.reset = true;
+    }
+}
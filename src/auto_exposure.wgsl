@@ -0,0 +1,128 @@
+const BIN_COUNT: u32 = 64u;
+// Scene luminance mapped to middle gray by the output exposure
+const MIDDLE_GRAY: f32 = 0.18;
+// Fixed point scale for metering weights accumulated in the histogram
+const WEIGHT_SCALE: f32 = 16.0;
+
+const METERING_AVERAGE: u32 = 0u;
+const METERING_CENTER_WEIGHTED: u32 = 1u;
+const METERING_SPOT: u32 = 2u;
+
+struct Settings {
+    min_ev: f32,
+    max_ev: f32,
+    low_fraction: f32,
+    high_fraction: f32,
+    // Maximum EV change this frame, towards brighter and darker scenes respectively
+    max_brighten: f32,
+    max_darken: f32,
+    compensation: f32,
+    spot_radius: f32,
+    metering: u32,
+    reset: u32,
+}
+
+@group(0) @binding(0) var<uniform> settings: Settings;
+@group(0) @binding(1) var color: texture_2d<f32>;
+@group(0) @binding(2) var<storage, read_write> histogram: array<atomic<u32>, BIN_COUNT>;
+@group(0) @binding(3) var<storage, read_write> adapted_ev: f32;
+@group(0) @binding(4) var output: texture_storage_2d<r32float, write>;
+
+var<workgroup> local_histogram: array<atomic<u32>, BIN_COUNT>;
+var<workgroup> bins: array<u32, BIN_COUNT>;
+
+fn metering_weight(uv: vec2<f32>) -> f32 {
+    let distance = length(uv - 0.5);
+    switch settings.metering {
+        case METERING_CENTER_WEIGHTED: {
+            return 1.0 - smoothstep(0.0, 0.75, distance) * 0.75;
+        }
+        case METERING_SPOT: {
+            return select(0.0, 1.0, distance <= settings.spot_radius);
+        }
+        case METERING_AVERAGE, default: {
+            return 1.0;
+        }
+    }
+}
+
+fn bin_ev(bin: u32) -> f32 {
+    return mix(settings.min_ev, settings.max_ev, (f32(bin) + 0.5) / f32(BIN_COUNT));
+}
+
+// Workgroup size must equal BIN_COUNT threads
+@compute @workgroup_size(8, 8, 1)
+fn compute_histogram(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    atomicStore(&local_histogram[local_index], 0u);
+    workgroupBarrier();
+
+    let size = textureDimensions(color);
+    if all(global_id.xy < size) {
+        let uv = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(size);
+        let weight = u32(metering_weight(uv) * WEIGHT_SCALE + 0.5);
+
+        let rgb = textureLoad(color, global_id.xy, 0).rgb;
+        let luminance = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+        let ev = log2(max(luminance, 1e-10));
+        let t = saturate((ev - settings.min_ev) / max(settings.max_ev - settings.min_ev, 1e-4));
+        let bin = min(u32(t * f32(BIN_COUNT)), BIN_COUNT - 1u);
+
+        if weight > 0u {
+            atomicAdd(&local_histogram[bin], weight);
+        }
+    }
+
+    workgroupBarrier();
+    atomicAdd(&histogram[local_index], atomicLoad(&local_histogram[local_index]));
+}
+
+@compute @workgroup_size(BIN_COUNT, 1, 1)
+fn compute_exposure(@builtin(local_invocation_index) local_index: u32) {
+    bins[local_index] = atomicLoad(&histogram[local_index]);
+    // Clear the histogram for the next frame
+    atomicStore(&histogram[local_index], 0u);
+    workgroupBarrier();
+
+    if local_index != 0u {
+        return;
+    }
+
+    var total = 0.0;
+    for (var i = 0u; i < BIN_COUNT; i++) {
+        total += f32(bins[i]);
+    }
+
+    // Average the bins between the low and high percentiles
+    let low = total * settings.low_fraction;
+    let high = total * settings.high_fraction;
+    var below = 0.0;
+    var ev_sum = 0.0;
+    var weight_sum = 0.0;
+    for (var i = 0u; i < BIN_COUNT; i++) {
+        let start = below;
+        let end = below + f32(bins[i]);
+        let included = max(min(end, high) - max(start, low), 0.0);
+        ev_sum += bin_ev(i) * included;
+        weight_sum += included;
+        below = end;
+    }
+
+    var ev = adapted_ev;
+    if weight_sum > 0.0 {
+        let target_ev = clamp(ev_sum / weight_sum, settings.min_ev, settings.max_ev);
+        if settings.reset != 0u {
+            ev = target_ev;
+        } else {
+            ev += clamp(target_ev - ev, -settings.max_darken, settings.max_brighten);
+        }
+    } else if settings.reset != 0u {
+        ev = clamp(0.0, settings.min_ev, settings.max_ev);
+    }
+
+    adapted_ev = ev;
+    let exposure = MIDDLE_GRAY * exp2(settings.compensation - ev);
+    textureStore(output, vec2(0u), vec4(exposure, 0.0, 0.0, 0.0));
+}
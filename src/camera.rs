@@ -0,0 +1,365 @@
+use crate::{
+    DlssRenderParameters, HaltonSequence, JitterGenerator, JitterSequence, jitter_projection,
+};
+use glam::{Mat4, Quat, UVec2, Vec2};
+use std::{
+    f32::consts::{FRAC_PI_4, PI},
+    time::{Duration, Instant},
+};
+
+/// Thresholds used by [`DlssCamera`] to detect camera cuts.
+///
+/// A change between two frames larger than any threshold resets DLSS's temporal history.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DlssCameraCutThresholds {
+    /// Maximum camera movement between frames, in world units.
+    pub max_translation: f32,
+    /// Maximum camera rotation between frames, in radians.
+    pub max_rotation: f32,
+    /// Maximum change of the vertical field of view between frames, in radians.
+    pub max_fov_change: f32,
+}
+
+impl Default for DlssCameraCutThresholds {
+    fn default() -> Self {
+        Self {
+            max_translation: 10.0,
+            max_rotation: FRAC_PI_4,
+            max_fov_change: PI / 18.0,
+        }
+    }
+}
+
+/// Tracks per-frame camera state needed by DLSS.
+///
+/// Call [`Self::update`] once per frame before rendering the camera, render using [`Self::jittered_projection`],
+/// then fill in the per-frame fields of [`DlssRenderParameters`] using [`Self::apply_to`].
+pub struct DlssCamera<S = HaltonSequence> {
+    pub cut_thresholds: DlssCameraCutThresholds,
+    jitter: JitterGenerator<S>,
+    frame_count: u64,
+    current: Option<CameraFrame>,
+    previous: Option<CameraFrame>,
+    last_update: Option<Instant>,
+    frame_time_delta: Option<Duration>,
+    jitter_offset: Vec2,
+    reset: bool,
+    reset_requested: bool,
+}
+
+#[derive(Clone, Copy)]
+struct CameraFrame {
+    view: Mat4,
+    projection: Mat4,
+    jittered_projection: Mat4,
+    render_resolution: UVec2,
+}
+
+impl DlssCamera<HaltonSequence> {
+    pub fn new() -> Self {
+        Self::with_jitter_sequence(HaltonSequence::default())
+    }
+}
+
+impl Default for DlssCamera<HaltonSequence> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: JitterSequence> DlssCamera<S> {
+    /// Create a [`DlssCamera`] using a custom jitter sequence.
+    pub fn with_jitter_sequence(sequence: S) -> Self {
+        Self {
+            cut_thresholds: DlssCameraCutThresholds::default(),
+            jitter: JitterGenerator::new(sequence),
+            frame_count: 0,
+            current: None,
+            previous: None,
+            last_update: None,
+            frame_time_delta: None,
+            jitter_offset: Vec2::ZERO,
+            reset: true,
+            reset_requested: false,
+        }
+    }
+
+    /// Begin a new frame.
+    ///
+    /// `view` is the world-to-view matrix, and `projection` the view-to-clip matrix, without jitter applied.
+    pub fn update(
+        &mut self,
+        view: Mat4,
+        projection: Mat4,
+        render_resolution: UVec2,
+        upscaled_resolution: UVec2,
+    ) {
+        self.update_at(
+            Instant::now(),
+            view,
+            projection,
+            render_resolution,
+            upscaled_resolution,
+        );
+    }
+
+    fn update_at(
+        &mut self,
+        now: Instant,
+        view: Mat4,
+        projection: Mat4,
+        render_resolution: UVec2,
+        upscaled_resolution: UVec2,
+    ) {
+        self.frame_time_delta = self.last_update.map(|last_update| now - last_update);
+        self.last_update = Some(now);
+
+        let jitter = self
+            .jitter
+            .next_jitter(render_resolution, upscaled_resolution);
+        let jittered = jitter_projection(projection, jitter, render_resolution);
+
+        self.previous = self.current;
+        self.current = Some(CameraFrame {
+            view,
+            projection,
+            jittered_projection: jittered.projection,
+            render_resolution,
+        });
+
+        self.jitter_offset = jittered.jitter_offset;
+        self.reset = match &self.previous {
+            Some(previous) => self.reset_requested || self.is_camera_cut(previous),
+            None => true,
+        };
+        self.reset_requested = false;
+        self.frame_count += 1;
+    }
+
+    /// Reset DLSS's temporal history on the next frame, e.g. after a scene change.
+    pub fn request_reset(&mut self) {
+        self.reset_requested = true;
+    }
+
+    /// Fill in the per-frame fields of [`DlssRenderParameters`] for the current frame.
+    ///
+    /// Sets [`DlssRenderParameters::jitter_offset`], [`DlssRenderParameters::reset`],
+    /// [`DlssRenderParameters::partial_texture_size`] and [`DlssRenderParameters::frame_time_delta`].
+    ///
+    /// [`DlssRenderParameters::motion_vector_scale`] is left unchanged, so that the context can derive it from
+    /// the partial texture size, its motion vector convention, and [`crate::DlssFeatureFlags::LowResolutionMotionVectors`].
+    pub fn apply_to(&self, render_parameters: &mut DlssRenderParameters) {
+        render_parameters.jitter_offset = self.jitter_offset;
+        render_parameters.reset = self.reset;
+        render_parameters.partial_texture_size = Some(self.render_resolution());
+        render_parameters.frame_time_delta = self.frame_time_delta;
+    }
+
+    /// Time between the last two calls to [`Self::update`], or `None` on the first frame.
+    pub fn frame_time_delta(&self) -> Option<Duration> {
+        self.frame_time_delta
+    }
+
+    /// Whether DLSS should reset its temporal history this frame.
+    pub fn reset(&self) -> bool {
+        self.reset
+    }
+
+    /// Subpixel jitter for the current frame, for use with [`DlssRenderParameters::jitter_offset`].
+    pub fn jitter_offset(&self) -> Vec2 {
+        self.jitter_offset
+    }
+
+    /// Number of calls to [`Self::update`] so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// The render resolution of the current frame.
+    pub fn render_resolution(&self) -> UVec2 {
+        self.current_frame().render_resolution
+    }
+
+    /// The current world-to-view matrix.
+    pub fn view(&self) -> Mat4 {
+        self.current_frame().view
+    }
+
+    /// The current view-to-clip matrix, without jitter.
+    pub fn projection(&self) -> Mat4 {
+        self.current_frame().projection
+    }
+
+    /// The current view-to-clip matrix with jitter applied, to render the camera with.
+    pub fn jittered_projection(&self) -> Mat4 {
+        self.current_frame().jittered_projection
+    }
+
+    /// The current world-to-clip matrix, without jitter.
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection() * self.view()
+    }
+
+    /// The previous frame's world-to-clip matrix, without jitter.
+    ///
+    /// Equal to [`Self::view_projection`] on the first frame.
+    pub fn previous_view_projection(&self) -> Mat4 {
+        let previous = self.previous.unwrap_or(*self.current_frame());
+        previous.projection * previous.view
+    }
+
+    fn current_frame(&self) -> &CameraFrame {
+        self.current
+            .as_ref()
+            .expect("DlssCamera::update must be called before accessing the current frame")
+    }
+
+    fn is_camera_cut(&self, previous: &CameraFrame) -> bool {
+        let current = self.current_frame();
+        let thresholds = &self.cut_thresholds;
+
+        let (_, previous_rotation, previous_position) =
+            previous.view.inverse().to_scale_rotation_translation();
+        let (_, current_rotation, current_position) =
+            current.view.inverse().to_scale_rotation_translation();
+        if previous_position.distance(current_position) > thresholds.max_translation {
+            return true;
+        }
+        if rotation_angle(previous_rotation, current_rotation) > thresholds.max_rotation {
+            return true;
+        }
+
+        match (
+            vertical_fov(previous.projection),
+            vertical_fov(current.projection),
+        ) {
+            (Some(previous_fov), Some(current_fov)) => {
+                (previous_fov - current_fov).abs() > thresholds.max_fov_change
+            }
+            (None, None) => false,
+            // Switched between perspective and orthographic projections
+            _ => true,
+        }
+    }
+}
+
+fn rotation_angle(a: Quat, b: Quat) -> f32 {
+    a.normalize().angle_between(b.normalize())
+}
+
+/// Returns `None` for orthographic projections.
+fn vertical_fov(projection: Mat4) -> Option<f32> {
+    let is_perspective = projection.row(3).w == 0.0;
+    is_perspective.then(|| 2.0 * (1.0 / projection.y_axis.y).atan())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    const RESOLUTION: UVec2 = UVec2::new(1920, 1080);
+
+    fn view(position: Vec3, yaw: f32) -> Mat4 {
+        Mat4::from_rotation_translation(Quat::from_rotation_y(yaw), position).inverse()
+    }
+
+    fn projection(fov: f32) -> Mat4 {
+        Mat4::perspective_infinite_reverse_rh(fov, 16.0 / 9.0, 0.1)
+    }
+
+    #[test]
+    fn first_frame_resets() {
+        let mut camera = DlssCamera::new();
+        camera.update(
+            view(Vec3::ZERO, 0.0),
+            projection(1.0),
+            RESOLUTION,
+            RESOLUTION,
+        );
+        assert!(camera.reset());
+        assert_eq!(camera.previous_view_projection(), camera.view_projection());
+
+        camera.update(
+            view(Vec3::X * 0.1, 0.01),
+            projection(1.0),
+            RESOLUTION,
+            RESOLUTION,
+        );
+        assert!(!camera.reset());
+        assert_eq!(camera.frame_count(), 2);
+    }
+
+    #[test]
+    fn detects_camera_cuts() {
+        let cases = [
+            (view(Vec3::X * 100.0, 0.0), projection(1.0)),
+            (view(Vec3::ZERO, 1.5), projection(1.0)),
+            (view(Vec3::ZERO, 0.0), projection(1.5)),
+            (
+                view(Vec3::ZERO, 0.0),
+                Mat4::orthographic_rh(-1.0, 1.0, -1.0, 1.0, 0.1, 10.0),
+            ),
+        ];
+
+        for (cut_view, cut_projection) in cases {
+            let mut camera = DlssCamera::new();
+            camera.update(
+                view(Vec3::ZERO, 0.0),
+                projection(1.0),
+                RESOLUTION,
+                RESOLUTION,
+            );
+            camera.update(cut_view, cut_projection, RESOLUTION, RESOLUTION);
+            assert!(camera.reset());
+        }
+    }
+
+    #[test]
+    fn measures_frame_time_between_updates() {
+        let mut camera = DlssCamera::new();
+        let start = Instant::now();
+        let update = |camera: &mut DlssCamera, now| {
+            camera.update_at(
+                now,
+                view(Vec3::ZERO, 0.0),
+                projection(1.0),
+                RESOLUTION,
+                RESOLUTION,
+            );
+        };
+
+        update(&mut camera, start);
+        assert_eq!(camera.frame_time_delta(), None);
+        update(&mut camera, start + Duration::from_millis(16));
+        assert_eq!(camera.frame_time_delta(), Some(Duration::from_millis(16)));
+        update(&mut camera, start + Duration::from_millis(50));
+        assert_eq!(camera.frame_time_delta(), Some(Duration::from_millis(34)));
+    }
+
+    #[test]
+    fn requested_reset_applies_to_one_frame() {
+        let mut camera = DlssCamera::new();
+        camera.update(
+            view(Vec3::ZERO, 0.0),
+            projection(1.0),
+            RESOLUTION,
+            RESOLUTION,
+        );
+        camera.request_reset();
+        camera.update(
+            view(Vec3::ZERO, 0.0),
+            projection(1.0),
+            RESOLUTION,
+            RESOLUTION,
+        );
+        assert!(camera.reset());
+        camera.update(
+            view(Vec3::ZERO, 0.0),
+            projection(1.0),
+            RESOLUTION,
+            RESOLUTION,
+        );
+        assert!(!camera.reset());
+    }
+}
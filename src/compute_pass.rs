@@ -0,0 +1,285 @@
+//! Shared helpers for the crate's built-in compute passes.
+
+use crate::DlssTexture;
+use glam::UVec2;
+use wgpu::{
+    BindGroup, BindGroupLayout, BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType,
+    BufferUsages, CommandEncoder, ComputePassDescriptor, ComputePipeline,
+    ComputePipelineDescriptor, Device, Extent3d, PipelineCompilationOptions,
+    PipelineLayoutDescriptor, SamplerBindingType, ShaderModule, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages, StorageTextureAccess, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension,
+    util::{BufferInitDescriptor, DeviceExt},
+};
+
+/// Workgroup size used by every built-in compute shader, on both axes.
+pub const WORKGROUP_SIZE: u32 = 8;
+
+pub fn create_shader_module(device: &Device, label: &str, source: &str) -> ShaderModule {
+    device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(label),
+        source: ShaderSource::Wgsl(source.into()),
+    })
+}
+
+pub fn create_compute_pipeline(
+    device: &Device,
+    label: &str,
+    module: &ShaderModule,
+    entry_point: &str,
+    bind_group_layout: &BindGroupLayout,
+) -> ComputePipeline {
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(label),
+        layout: Some(&pipeline_layout),
+        module,
+        entry_point: Some(entry_point),
+        compilation_options: PipelineCompilationOptions::default(),
+        cache: None,
+    })
+}
+
+/// Dispatch one thread per pixel of `size`.
+pub fn dispatch(
+    command_encoder: &mut CommandEncoder,
+    label: &str,
+    pipeline: &ComputePipeline,
+    bind_group: &BindGroup,
+    size: UVec2,
+) {
+    let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+        label: Some(label),
+        timestamp_writes: None,
+    });
+    compute_pass.set_pipeline(pipeline);
+    compute_pass.set_bind_group(0, bind_group, &[]);
+    compute_pass.dispatch_workgroups(
+        size.x.div_ceil(WORKGROUP_SIZE),
+        size.y.div_ceil(WORKGROUP_SIZE),
+        1,
+    );
+}
+
+pub fn texture_entry(binding: u32, sample_type: TextureSampleType) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Texture {
+            sample_type,
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+pub fn multisampled_texture_entry(
+    binding: u32,
+    sample_type: TextureSampleType,
+) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Texture {
+            sample_type,
+            view_dimension: TextureViewDimension::D2,
+            multisampled: true,
+        },
+        count: None,
+    }
+}
+
+pub fn sampler_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+        count: None,
+    }
+}
+
+pub fn storage_texture_entry(binding: u32, format: TextureFormat) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::StorageTexture {
+            access: StorageTextureAccess::WriteOnly,
+            format,
+            view_dimension: TextureViewDimension::D2,
+        },
+        count: None,
+    }
+}
+
+pub fn storage_buffer_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub fn uniform_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// Declaration that [`shader_source_with_output_format`] replaces, which shaders use to declare their output texture.
+const OUTPUT_TEXTURE_ALIAS: &str = "alias OutputTexture = texture_storage_2d<rgba16float, write>;";
+
+/// Adapt a shader's `OutputTexture` alias to a storage texture format chosen at runtime.
+///
+/// Returns `None` if the format cannot be written as a storage texture without additional features.
+pub fn shader_source_with_output_format(source: &str, format: TextureFormat) -> Option<String> {
+    let storage_format = storage_format_name(format)?;
+    debug_assert!(source.contains(OUTPUT_TEXTURE_ALIAS));
+
+    Some(source.replace(
+        OUTPUT_TEXTURE_ALIAS,
+        &format!("alias OutputTexture = texture_storage_2d<{storage_format}, write>;"),
+    ))
+}
+
+/// Whether a format can be used for the output of [`shader_source_with_output_format`].
+pub fn is_output_format(format: TextureFormat) -> bool {
+    storage_format_name(format).is_some()
+}
+
+fn storage_format_name(format: TextureFormat) -> Option<&'static str> {
+    match format {
+        TextureFormat::Rgba8Unorm => Some("rgba8unorm"),
+        TextureFormat::Rgba16Float => Some("rgba16float"),
+        TextureFormat::Rgba32Float => Some("rgba32float"),
+        _ => None,
+    }
+}
+
+/// Create a uniform buffer from a list of 4 byte values, which must follow WGSL's layout rules.
+///
+/// A new buffer is created per use, so that multiple passes can be encoded per submission with different values.
+pub fn create_uniform_buffer(device: &Device, label: &str, values: &[[u8; 4]]) -> Buffer {
+    device.create_buffer_init(&BufferInitDescriptor {
+        label: Some(label),
+        contents: values.as_flattened(),
+        usage: BufferUsages::UNIFORM,
+    })
+}
+
+/// A 2D texture owned by a compute pass, recreated whenever the requested size changes.
+pub struct ScratchTexture {
+    label: &'static str,
+    format: TextureFormat,
+    usage: TextureUsages,
+    texture: Option<(Texture, TextureView)>,
+}
+
+impl ScratchTexture {
+    pub fn new(label: &'static str, format: TextureFormat, usage: TextureUsages) -> Self {
+        Self {
+            label,
+            format,
+            usage,
+            texture: None,
+        }
+    }
+
+    /// Recreate the texture if it does not exist yet or has a different size.
+    pub fn resize(&mut self, size: UVec2, device: &Device) {
+        let stale = self
+            .texture
+            .as_ref()
+            .is_none_or(|(texture, _)| texture.width() != size.x || texture.height() != size.y);
+
+        if stale {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some(self.label),
+                size: Extent3d {
+                    width: size.x.max(1),
+                    height: size.y.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: self.format,
+                usage: self.usage,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            self.texture = Some((texture, view));
+        }
+    }
+
+    /// The texture, which must have been created by [`Self::resize`].
+    pub fn texture(&self) -> DlssTexture<'_> {
+        let (texture, view) = self
+            .texture
+            .as_ref()
+            .expect("ScratchTexture::resize must be called before use");
+        DlssTexture::new(texture, view)
+    }
+}
+
+/// Convert a value to the 4 byte representation expected by [`create_uniform_buffer`].
+pub trait UniformValue {
+    fn uniform_words(&self) -> Vec<[u8; 4]>;
+}
+
+impl UniformValue for f32 {
+    fn uniform_words(&self) -> Vec<[u8; 4]> {
+        vec![self.to_ne_bytes()]
+    }
+}
+
+impl UniformValue for u32 {
+    fn uniform_words(&self) -> Vec<[u8; 4]> {
+        vec![self.to_ne_bytes()]
+    }
+}
+
+impl UniformValue for glam::Vec2 {
+    fn uniform_words(&self) -> Vec<[u8; 4]> {
+        self.to_array().iter().map(|x| x.to_ne_bytes()).collect()
+    }
+}
+
+impl UniformValue for glam::UVec2 {
+    fn uniform_words(&self) -> Vec<[u8; 4]> {
+        self.to_array().iter().map(|x| x.to_ne_bytes()).collect()
+    }
+}
+
+impl UniformValue for glam::Vec4 {
+    fn uniform_words(&self) -> Vec<[u8; 4]> {
+        self.to_array().iter().map(|x| x.to_ne_bytes()).collect()
+    }
+}
+
+impl UniformValue for glam::Mat4 {
+    fn uniform_words(&self) -> Vec<[u8; 4]> {
+        self.to_cols_array()
+            .iter()
+            .map(|x| x.to_ne_bytes())
+            .collect()
+    }
+}
@@ -1,18 +1,93 @@
-use crate::{DlssExposure, DlssRenderParameters, DlssSdk, nvsdk_ngx::*};
+use crate::{
+    DlssExposure, DlssGuideTextures, DlssMotionVectorDilationPass, DlssRenderParameters, DlssSdk,
+    DlssSharpeningPass, DlssTexture, HaltonSequence, JitterSequence, MotionVectorConvention,
+    compute_pass::{ScratchTexture, is_output_format},
+    feature_info::DlssFeature,
+    intermediate_output::{IntermediateOutput, transfer_mode},
+    jitter::jitter_phase_count,
+    nvsdk_ngx::*,
+};
 use glam::{UVec2, Vec2};
 use std::{
-    iter,
+    iter, mem,
     ops::RangeInclusive,
     ptr,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use wgpu::{
+    Adapter, CommandEncoder, CommandEncoderDescriptor, Device, Extent3d, Queue,
+    TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureView, TextureViewDescriptor, hal::api::Vulkan,
 };
-use wgpu::{Adapter, CommandEncoder, CommandEncoderDescriptor, Device, Queue, hal::api::Vulkan};
+
+/// Settings for the on-screen indicator drawn by the development version of DLSS.
+///
+/// Only used with the `debug_overlay` feature, which loads the development library. Without it, these settings are ignored.
+/// Toggling the overlay and selecting its debug views are not supported: NGX exposes no evaluation parameters for them,
+/// so they remain controlled by the development library's keyboard shortcuts.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct DlssDebugOptions {
+    /// Mirror the indicator horizontally.
+    pub invert_indicator_x_axis: bool,
+    /// Mirror the indicator vertically, e.g. for output textures that are presented with Y pointing up.
+    pub invert_indicator_y_axis: bool,
+}
+
+/// Settings for output textures allocated by a [`DlssContext`], see [`DlssContext::set_owned_output`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct DlssOwnedOutputDescriptor {
+    pub format: TextureFormat,
+    /// Usages needed to consume the output, e.g. [`TextureUsages::TEXTURE_BINDING`] to sample it.
+    ///
+    /// [`TextureUsages::STORAGE_BINDING`] is added if the format supports it, and [`TextureUsages::RENDER_ATTACHMENT`] otherwise.
+    pub usage: TextureUsages,
+    /// Number of output textures to cycle through, so that the output of frames still in flight is not overwritten.
+    pub ring_size: usize,
+}
+
+impl Default for DlssOwnedOutputDescriptor {
+    fn default() -> Self {
+        Self {
+            format: TextureFormat::Rgba16Float,
+            usage: TextureUsages::TEXTURE_BINDING,
+            ring_size: 1,
+        }
+    }
+}
+
+/// An output texture owned by a [`DlssContext`].
+#[derive(Clone, Debug)]
+pub struct DlssOwnedOutput {
+    pub texture: Texture,
+    pub view: TextureView,
+}
+
+impl DlssOwnedOutput {
+    /// Wrap this output for use as [`DlssRenderParameters::dlss_output`].
+    pub fn dlss_texture(&self) -> DlssTexture<'_> {
+        DlssTexture::new(&self.texture, &self.view)
+    }
+}
 
 /// Camera-specific object for using DLSS.
 pub struct DlssContext {
-    upscaled_resolution: UVec2,
-    min_render_resolution: UVec2,
-    max_render_resolution: UVec2,
+    config: FeatureConfig,
+    motion_vector_convention: MotionVectorConvention,
+    motion_vector_dilation: Option<DlssMotionVectorDilationPass>,
+    feature_flag_check: bool,
+    feature_flag_check_pending: bool,
+    reset_pending: bool,
+    exposure_texture: ExposureTexture,
+    tone_mapper_type: DlssToneMapperType,
+    debug_options: DlssDebugOptions,
+    sharpness: Option<f32>,
+    sharpening_pass: DlssSharpeningPass,
+    /// DLSS output when sharpening, which is then sharpened into [`DlssRenderParameters::dlss_output`].
+    sharpening_input: ScratchTexture,
+    intermediate_output: IntermediateOutput,
+    owned_output: Option<OwnedOutputRing>,
+    last_render: Option<Instant>,
     device: Device,
     sdk: Arc<Mutex<DlssSdk>>,
     feature: *mut NVSDK_NGX_Handle,
@@ -32,40 +107,21 @@ impl DlssContext {
     ) -> Result<Self, DlssError> {
         let locked_sdk = sdk.lock().unwrap();
 
-        let perf_quality_value = perf_quality_mode.as_perf_quality_value(upscaled_resolution);
-
-        let mut optimal_render_resolution = UVec2::ZERO;
-        let mut min_render_resolution = UVec2::ZERO;
-        let mut max_render_resolution = UVec2::ZERO;
-        unsafe {
-            let mut deprecated_sharpness = 0.0f32;
-            check_ngx_result(NGX_DLSS_GET_OPTIMAL_SETTINGS(
-                locked_sdk.parameters,
-                upscaled_resolution.x,
-                upscaled_resolution.y,
-                perf_quality_value,
-                &mut optimal_render_resolution.x,
-                &mut optimal_render_resolution.y,
-                &mut max_render_resolution.x,
-                &mut max_render_resolution.y,
-                &mut min_render_resolution.x,
-                &mut min_render_resolution.y,
-                &mut deprecated_sharpness,
-            ))?;
-        }
-        if perf_quality_mode == DlssPerfQualityMode::Dlaa {
-            optimal_render_resolution = upscaled_resolution;
-            min_render_resolution = upscaled_resolution;
-            max_render_resolution = upscaled_resolution;
-        }
+        let config = FeatureConfig::new(
+            &locked_sdk,
+            DlssFeature::SuperResolution,
+            upscaled_resolution,
+            perf_quality_mode,
+            feature_flags,
+        )?;
 
         let mut dlss_create_params = NVSDK_NGX_DLSS_Create_Params {
             Feature: NVSDK_NGX_Feature_Create_Params {
-                InWidth: optimal_render_resolution.x,
-                InHeight: optimal_render_resolution.y,
+                InWidth: config.optimal_render_resolution.x,
+                InHeight: config.optimal_render_resolution.y,
                 InTargetWidth: upscaled_resolution.x,
                 InTargetHeight: upscaled_resolution.y,
-                InPerfQualityValue: perf_quality_value,
+                InPerfQualityValue: perf_quality_mode.as_perf_quality_value(upscaled_resolution),
             },
             InFeatureCreateFlags: feature_flags.as_flags(),
             InEnableOutputSubrects: feature_flags.contains(DlssFeatureFlags::OutputSubrect),
@@ -77,57 +133,171 @@ impl DlssContext {
 
         let mut feature = ptr::null_mut();
         unsafe {
-            command_encoder.as_hal_mut::<Vulkan, _, _>(|command_encoder| {
-                check_ngx_result(NGX_VULKAN_CREATE_DLSS_EXT(
-                    command_encoder.unwrap().raw_handle(),
-                    1,
-                    1,
-                    &mut feature,
-                    locked_sdk.parameters,
-                    &mut dlss_create_params,
-                ))
-            })?
+            command_encoder
+                .as_hal_mut::<Vulkan, _, _>(|command_encoder| {
+                    check_ngx_result(
+                        "NGX_VULKAN_CREATE_DLSS_EXT",
+                        NGX_VULKAN_CREATE_DLSS_EXT(
+                            command_encoder.unwrap().raw_handle(),
+                            1,
+                            1,
+                            &mut feature,
+                            locked_sdk.parameters,
+                            &mut dlss_create_params,
+                        ),
+                    )
+                })
+                .map_err(|e| {
+                    e.with_context(config.error_context(Some(config.optimal_render_resolution)))
+                })?
         }
 
         queue.submit([command_encoder.finish()]);
 
         Ok(Self {
-            upscaled_resolution,
-            min_render_resolution,
-            max_render_resolution,
+            config,
+            motion_vector_convention: MotionVectorConvention::default(),
+            motion_vector_dilation: None,
+            feature_flag_check: true,
+            feature_flag_check_pending: true,
+            reset_pending: false,
+            exposure_texture: ExposureTexture::new(queue),
+            tone_mapper_type: DlssToneMapperType::default(),
+            debug_options: DlssDebugOptions::default(),
+            sharpness: None,
+            sharpening_pass: DlssSharpeningPass::new(),
+            sharpening_input: ScratchTexture::new(
+                "dlss_sharpening_input",
+                TextureFormat::Rgba16Float,
+                TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            ),
+            intermediate_output: IntermediateOutput::new(device),
+            owned_output: None,
+            last_render: None,
             device: device.clone(),
             sdk: Arc::clone(&sdk),
             feature,
         })
     }
 
+    /// Recreate the DLSS feature with new settings, keeping settings changed through setters.
+    ///
+    /// Owned outputs enabled with [`Self::set_owned_output`] are reallocated if the upscaled resolution changed.
+    /// If creating the feature fails, the context is left unchanged. Temporal history is reset.
+    pub fn reconfigure(
+        &mut self,
+        upscaled_resolution: UVec2,
+        perf_quality_mode: DlssPerfQualityMode,
+        feature_flags: DlssFeatureFlags,
+        device: &Device,
+        queue: &Queue,
+    ) -> Result<(), DlssError> {
+        let mut context = Self::new(
+            upscaled_resolution,
+            perf_quality_mode,
+            feature_flags,
+            Arc::clone(&self.sdk),
+            device,
+            queue,
+        )?;
+        context.inherit_settings(self);
+        // Dropping the old context releases its feature
+        *self = context;
+        Ok(())
+    }
+
+    /// Carry over settings changed through setters from `old`, which this context replaces.
+    pub(crate) fn inherit_settings(&mut self, old: &mut DlssContext) {
+        self.motion_vector_convention = old.motion_vector_convention;
+        self.motion_vector_dilation = old.motion_vector_dilation.take();
+        self.tone_mapper_type = old.tone_mapper_type;
+        self.debug_options = old.debug_options;
+        self.sharpness = old.sharpness;
+        self.set_feature_flag_check(old.feature_flag_check);
+        self.owned_output = old.owned_output.take().map(|ring| {
+            if ring.size == self.config.upscaled_resolution {
+                ring
+            } else {
+                OwnedOutputRing::new(
+                    ring.descriptor,
+                    self.config.upscaled_resolution,
+                    &self.device,
+                )
+            }
+        });
+    }
+
     /// Encode commands to render DLSS.
+    ///
+    /// If [`DlssRenderParameters::dlss_output`] lacks [`TextureUsages::STORAGE_BINDING`], such as an sRGB swapchain texture,
+    /// DLSS outputs to an internal texture that is then copied into it, or drawn to it with format conversion.
+    /// This requires [`TextureUsages::COPY_DST`] or [`TextureUsages::RENDER_ATTACHMENT`] respectively.
     pub fn render(
         &mut self,
         render_parameters: DlssRenderParameters,
         command_encoder: &mut CommandEncoder,
         adapter: &Adapter,
     ) -> Result<(), DlssError> {
-        render_parameters.validate()?;
+        self.validate_render_parameters(&render_parameters)?;
+        if mem::take(&mut self.feature_flag_check_pending) {
+            self.check_feature_flags(&render_parameters);
+        }
+
+        if let Some(dilation_pass) = &mut self.motion_vector_dilation {
+            dilation_pass.encode(
+                &render_parameters.depth,
+                &render_parameters.motion_vectors,
+                self.config
+                    .feature_flags
+                    .contains(DlssFeatureFlags::InvertedDepth),
+                render_parameters.partial_texture_size,
+                command_encoder,
+                &self.device,
+            )?;
+        }
+
+        self.exposure_texture
+            .update(&render_parameters.exposure, &self.device);
+        let target = render_parameters.dlss_output;
+        let use_intermediate_output = !target.is_storage();
+        if use_intermediate_output {
+            self.intermediate_output.prepare(&target, &self.device)?;
+        }
+        if self.sharpness.is_some() {
+            self.sharpening_input.resize(target.size(), &self.device);
+        }
+        let output = if use_intermediate_output {
+            self.intermediate_output.texture()
+        } else {
+            target
+        };
+        let render_parameters = DlssRenderParameters {
+            exposure: self.exposure_texture.resolve(render_parameters.exposure),
+            motion_vectors: match &self.motion_vector_dilation {
+                Some(dilation_pass) => dilation_pass.output(),
+                None => render_parameters.motion_vectors,
+            },
+            dlss_output: match self.sharpness {
+                Some(_) => self.sharpening_input.texture(),
+                None => output,
+            },
+            ..render_parameters
+        };
+
+        let reset = render_parameters.reset || mem::take(&mut self.reset_pending);
+        let frame_time_delta =
+            frame_time_delta(&mut self.last_render, render_parameters.frame_time_delta);
+        let debug_options = self.debug_options.effective();
 
         let sdk = self.sdk.lock().unwrap();
 
-        let partial_texture_size = render_parameters
-            .partial_texture_size
-            .unwrap_or(self.max_render_resolution);
+        let partial_texture_size = self.config.partial_texture_size(&render_parameters);
+        let motion_vector_scale = self
+            .config
+            .motion_vector_scale(&render_parameters, self.motion_vector_convention);
 
-        let (exposure, exposure_scale, pre_exposure) = match &render_parameters.exposure {
-            DlssExposure::Manual {
-                exposure,
-                exposure_scale,
-                pre_exposure,
-            } => (
-                &mut exposure.as_resource(adapter) as *mut _,
-                exposure_scale.unwrap_or(1.0),
-                pre_exposure.unwrap_or(0.0),
-            ),
-            DlssExposure::Automatic => (ptr::null_mut(), 0.0, 0.0),
-        };
+        let mut exposure = ExposureResource::new(&render_parameters.exposure, adapter);
+        let mut guides = GuideResources::new(&render_parameters.guides, adapter);
 
         let mut dlss_eval_params = NVSDK_NGX_VK_DLSS_Eval_Params {
             Feature: NVSDK_NGX_VK_Feature_Eval_Params {
@@ -143,11 +313,11 @@ impl DlssContext {
                 Width: partial_texture_size.x,
                 Height: partial_texture_size.y,
             },
-            InReset: render_parameters.reset as _,
-            InMVScaleX: render_parameters.motion_vector_scale.unwrap_or(Vec2::ONE).x,
-            InMVScaleY: render_parameters.motion_vector_scale.unwrap_or(Vec2::ONE).y,
-            pInTransparencyMask: ptr::null_mut(),
-            pInExposureTexture: exposure,
+            InReset: reset as _,
+            InMVScaleX: motion_vector_scale.x,
+            InMVScaleY: motion_vector_scale.y,
+            pInTransparencyMask: resource_ptr(&mut guides.transparency_mask),
+            pInExposureTexture: resource_ptr(&mut exposure.texture),
             pInBiasCurrentColorMask: match &render_parameters.bias {
                 Some(bias) => &mut bias.as_resource(adapter),
                 None => ptr::null_mut(),
@@ -158,97 +328,609 @@ impl DlssContext {
             InTranslucencySubrectBase: NVSDK_NGX_Coordinates { X: 0, Y: 0 },
             InBiasCurrentColorSubrectBase: NVSDK_NGX_Coordinates { X: 0, Y: 0 },
             InOutputSubrectBase: NVSDK_NGX_Coordinates { X: 0, Y: 0 },
-            InPreExposure: pre_exposure,
-            InExposureScale: exposure_scale,
-            InIndicatorInvertXAxis: 0,
-            InIndicatorInvertYAxis: 0,
+            InPreExposure: exposure.pre_exposure,
+            InExposureScale: exposure.exposure_scale,
+            InIndicatorInvertXAxis: debug_options.invert_indicator_x_axis as _,
+            InIndicatorInvertYAxis: debug_options.invert_indicator_y_axis as _,
             GBufferSurface: NVSDK_NGX_VK_GBuffer {
-                pInAttrib: [ptr::null_mut(); 16],
+                pInAttrib: guides.gbuffer.each_mut().map(resource_ptr),
             },
-            InToneMapperType: NVSDK_NGX_ToneMapperType_NVSDK_NGX_TONEMAPPER_STRING,
-            pInMotionVectors3D: ptr::null_mut(),
-            pInIsParticleMask: ptr::null_mut(),
-            pInAnimatedTextureMask: ptr::null_mut(),
-            pInDepthHighRes: ptr::null_mut(),
-            pInPositionViewSpace: ptr::null_mut(),
-            InFrameTimeDeltaInMsec: 0.0,
-            pInRayTracingHitDistance: ptr::null_mut(),
-            pInMotionVectorsReflections: ptr::null_mut(),
+            InToneMapperType: self.tone_mapper_type.as_tone_mapper_type(),
+            pInMotionVectors3D: resource_ptr(&mut guides.motion_vectors_3d),
+            pInIsParticleMask: resource_ptr(&mut guides.particle_mask),
+            pInAnimatedTextureMask: resource_ptr(&mut guides.animated_texture_mask),
+            pInDepthHighRes: resource_ptr(&mut guides.depth_high_res),
+            pInPositionViewSpace: resource_ptr(&mut guides.position_view_space),
+            InFrameTimeDeltaInMsec: frame_time_delta.as_secs_f32() * 1000.0,
+            pInRayTracingHitDistance: resource_ptr(&mut guides.ray_tracing_hit_distance),
+            pInMotionVectorsReflections: resource_ptr(&mut guides.motion_vectors_reflections),
         };
 
         command_encoder.transition_resources(iter::empty(), render_parameters.barrier_list());
         unsafe {
             command_encoder.as_hal_mut::<Vulkan, _, _>(|command_encoder| {
-                check_ngx_result(NGX_VULKAN_EVALUATE_DLSS_EXT(
-                    command_encoder.unwrap().raw_handle(),
-                    self.feature,
-                    sdk.parameters,
-                    &mut dlss_eval_params,
-                ))
+                check_ngx_result(
+                    "NGX_VULKAN_EVALUATE_DLSS_EXT",
+                    NGX_VULKAN_EVALUATE_DLSS_EXT(
+                        command_encoder.unwrap().raw_handle(),
+                        self.feature,
+                        sdk.parameters,
+                        &mut dlss_eval_params,
+                    ),
+                )
             })
         }
+        .map_err(|e| e.with_context(self.config.error_context(Some(partial_texture_size))))?;
+
+        if let Some(sharpness) = self.sharpness {
+            self.sharpening_pass.encode(
+                &render_parameters.dlss_output,
+                &output,
+                sharpness,
+                self.config
+                    .feature_flags
+                    .contains(DlssFeatureFlags::HighDynamicRange),
+                command_encoder,
+                &self.device,
+            )?;
+        }
+        if use_intermediate_output {
+            self.intermediate_output
+                .transfer(&target, command_encoder, &self.device);
+        }
+
+        Ok(())
+    }
+
+    /// Reset temporal history on the next call to [`Self::render`], like [`DlssRenderParameters::reset`].
+    pub fn reset(&mut self) {
+        self.reset_pending = true;
     }
 
     /// Suggested subpixel camera jitter for a given frame.
+    ///
+    /// Uses a Halton(2, 3) sequence. See [`crate::JitterGenerator`] for other sequences.
     pub fn suggested_jitter(&self, frame_number: u32, render_resolution: UVec2) -> Vec2 {
-        let ratio = self.upscaled_resolution.x as f32 / render_resolution.x as f32;
-        let phase_count = (8.0 * ratio * ratio) as u32;
-        let i = frame_number % phase_count;
-
-        Vec2 {
-            x: halton_sequence(i, 2),
-            y: halton_sequence(i, 3),
-        } - 0.5
+        self.config
+            .suggested_jitter(frame_number, render_resolution)
     }
 
     /// Suggested mip bias for sampling textures.
     pub fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
-        (render_resolution.x as f32 / self.upscaled_resolution.x as f32).log2() - 1.0
+        self.config.suggested_mip_bias(render_resolution)
     }
 
     /// The upscaled resolution DLSS will output at.
     pub fn upscaled_resolution(&self) -> UVec2 {
-        self.upscaled_resolution
+        self.config.upscaled_resolution
     }
 
     /// The resolution the camera should render at, pre-upscaling.
     pub fn render_resolution(&self) -> UVec2 {
-        self.min_render_resolution
+        self.config.min_render_resolution
     }
 
     /// Like [`Self::render_resolution`], but returns a range of values for use with dynamic resolution scaling.
+    pub fn render_resolution_range(&self) -> RangeInclusive<UVec2> {
+        self.config.render_resolution_range()
+    }
+
+    /// Describe how [`DlssRenderParameters::motion_vectors`] are stored.
+    ///
+    /// Used to compute the motion vector scale each frame, unless [`DlssRenderParameters::motion_vector_scale`] is set.
+    pub fn set_motion_vector_convention(&mut self, convention: MotionVectorConvention) {
+        self.motion_vector_convention = convention;
+    }
+
+    pub fn motion_vector_convention(&self) -> MotionVectorConvention {
+        self.motion_vector_convention
+    }
+
+    /// Whether to dilate [`DlssRenderParameters::motion_vectors`] before DLSS, see [`DlssMotionVectorDilationPass`].
+    ///
+    /// Disabled by default. Requires motion vectors at render resolution, i.e. [`DlssFeatureFlags::LowResolutionMotionVectors`].
+    pub fn set_motion_vector_dilation(&mut self, enabled: bool) {
+        if enabled != self.motion_vector_dilation.is_some() {
+            self.motion_vector_dilation =
+                enabled.then(|| DlssMotionVectorDilationPass::new(&self.device));
+        }
+    }
+
+    pub fn motion_vector_dilation(&self) -> bool {
+        self.motion_vector_dilation.is_some()
+    }
+
+    /// The tonemapper applied to DLSS's output, used as a hint to improve quality.
+    pub fn set_tone_mapper_type(&mut self, tone_mapper_type: DlssToneMapperType) {
+        self.tone_mapper_type = tone_mapper_type;
+    }
+
+    pub fn tone_mapper_type(&self) -> DlssToneMapperType {
+        self.tone_mapper_type
+    }
+
+    /// Have this context allocate its own output textures of [`Self::upscaled_resolution`], or stop doing so with `None`.
+    ///
+    /// Each frame, call [`Self::acquire_owned_output`] and use the result as [`DlssRenderParameters::dlss_output`].
+    /// The textures are dropped with the context, and reallocated by [`Self::reconfigure`] if the upscaled resolution changes.
+    pub fn set_owned_output(&mut self, descriptor: Option<DlssOwnedOutputDescriptor>) {
+        self.owned_output = descriptor.map(|descriptor| {
+            OwnedOutputRing::new(descriptor, self.config.upscaled_resolution, &self.device)
+        });
+    }
+
+    pub fn owned_output_descriptor(&self) -> Option<DlssOwnedOutputDescriptor> {
+        self.owned_output.as_ref().map(|ring| ring.descriptor)
+    }
+
+    /// Advance to the next texture in the ring of owned outputs, and return it for use in the next call to [`Self::render`].
+    ///
+    /// Returns `None` unless enabled with [`Self::set_owned_output`].
+    pub fn acquire_owned_output(&mut self) -> Option<DlssOwnedOutput> {
+        let ring = self.owned_output.as_mut()?;
+        ring.current = (ring.current + 1) % ring.outputs.len();
+        Some(ring.outputs[ring.current].clone())
+    }
+
+    /// The owned output most recently returned by [`Self::acquire_owned_output`], i.e. the output of the last render.
+    pub fn owned_output(&self) -> Option<&DlssOwnedOutput> {
+        self.owned_output
+            .as_ref()
+            .map(|ring| &ring.outputs[ring.current])
+    }
+
+    /// Strength of contrast adaptive sharpening applied to DLSS's output, between 0.0 and 1.0.
+    ///
+    /// Disabled (`None`) by default, as DLSS itself no longer sharpens. When enabled, [`DlssRenderParameters::dlss_output`]
+    /// must use one of the `Rgba8Unorm`, `Rgba16Float` or `Rgba32Float` formats. See [`DlssSharpeningPass`].
+    pub fn set_sharpness(&mut self, sharpness: Option<f32>) {
+        self.sharpness = sharpness.map(|sharpness| sharpness.clamp(0.0, 1.0));
+    }
+
+    pub fn sharpness(&self) -> Option<f32> {
+        self.sharpness
+    }
+
+    /// Settings for the development version of DLSS. Has no effect without the `debug_overlay` feature.
+    pub fn set_debug_options(&mut self, debug_options: DlssDebugOptions) {
+        self.debug_options = debug_options;
+    }
+
+    pub fn debug_options(&self) -> DlssDebugOptions {
+        self.debug_options
+    }
+
+    /// Whether the next call to [`Self::render`] logs a warning if the [`DlssFeatureFlags`] this context was created with
+    /// contradict the textures it is given. See [`DlssFeatureFlags::infer`].
+    ///
+    /// Enabled by default, so that the first render after [`Self::new`] is checked. Only the first render after enabling,
+    /// or after [`Self::reconfigure`] while enabled, is checked, so the check costs nothing afterwards. Disable it before
+    /// the first render to opt out.
+    pub fn set_feature_flag_check(&mut self, enabled: bool) {
+        self.feature_flag_check = enabled;
+        self.feature_flag_check_pending = enabled;
+    }
+
+    pub fn feature_flag_check(&self) -> bool {
+        self.feature_flag_check
+    }
+
+    /// Check everything that can make [`Self::render`] fail before it encodes any commands, other than NGX itself.
+    pub(crate) fn validate_render_parameters(
+        &self,
+        render_parameters: &DlssRenderParameters,
+    ) -> Result<(), DlssError> {
+        render_parameters.validate()?;
+
+        let motion_vector_size = render_parameters.motion_vectors.size();
+        let depth_size = render_parameters.depth.size();
+        if self.motion_vector_dilation.is_some() && motion_vector_size != depth_size {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "motion vector dilation requires motion_vectors to be the same size as depth, but they have sizes {motion_vector_size} and {depth_size}"
+            )));
+        }
+
+        let target = &render_parameters.dlss_output;
+        if target.is_storage() {
+            if self.sharpness.is_some() && !is_output_format(target.format()) {
+                return Err(DlssError::InvalidRenderParameters(format!(
+                    "sharpening requires a dlss_output format of Rgba8Unorm, Rgba16Float or Rgba32Float, but it is {:?}",
+                    target.format()
+                )));
+            }
+        } else {
+            transfer_mode(target)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_feature_flags(&self, render_parameters: &DlssRenderParameters) {
+        let inferred = DlssFeatureFlags::from_textures(
+            render_parameters.color.format(),
+            render_parameters.motion_vectors.size(),
+            self.config.upscaled_resolution,
+        );
+
+        let mut checked = DlssFeatureFlags::TEXTURE_INFERRED;
+        // Motion vector resolution is ambiguous when not upscaling
+        if self.config.max_render_resolution == self.config.upscaled_resolution {
+            checked.remove(DlssFeatureFlags::LowResolutionMotionVectors);
+        }
+
+        let mismatched = (inferred ^ self.config.feature_flags) & checked;
+        if !mismatched.is_empty() {
+            log::warn!(
+                "DlssContext was created with feature flags {:?}, but the color format {:?} and motion vector size {} suggest {:?}. Mismatched flags: {:?}",
+                self.config.feature_flags & checked,
+                render_parameters.color.format(),
+                render_parameters.motion_vectors.size(),
+                inferred & checked,
+                mismatched,
+            );
+        }
+    }
+}
+
+/// Output textures allocated by [`DlssContext::set_owned_output`].
+struct OwnedOutputRing {
+    descriptor: DlssOwnedOutputDescriptor,
+    size: UVec2,
+    outputs: Vec<DlssOwnedOutput>,
+    current: usize,
+}
+
+impl OwnedOutputRing {
+    fn new(descriptor: DlssOwnedOutputDescriptor, size: UVec2, device: &Device) -> Self {
+        let mut usage = descriptor.usage;
+        if descriptor
+            .format
+            .guaranteed_format_features(device.features())
+            .allowed_usages
+            .contains(TextureUsages::STORAGE_BINDING)
+        {
+            usage |= TextureUsages::STORAGE_BINDING;
+        } else {
+            usage |= TextureUsages::RENDER_ATTACHMENT;
+        }
+
+        let outputs = (0..descriptor.ring_size.max(1))
+            .map(|_| {
+                let texture = device.create_texture(&TextureDescriptor {
+                    label: Some("dlss_output"),
+                    size: Extent3d {
+                        width: size.x,
+                        height: size.y,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: descriptor.format,
+                    usage,
+                    view_formats: &[],
+                });
+                let view = texture.create_view(&TextureViewDescriptor::default());
+                DlssOwnedOutput { texture, view }
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            descriptor,
+            size,
+            // So that the first acquired output is the first in the ring
+            current: outputs.len() - 1,
+            outputs,
+        }
+    }
+}
+
+impl DlssDebugOptions {
+    /// The options to pass to NGX, which are ignored without the `debug_overlay` feature.
+    pub(crate) fn effective(self) -> Self {
+        if cfg!(feature = "debug_overlay") {
+            self
+        } else {
+            Self::default()
+        }
+    }
+}
+
+/// The settings an NGX feature is created with, and the render resolutions NGX recommends for them.
+///
+/// Shared by [`DlssContext`] and [`crate::DlssRayReconstructionContext`].
+pub(crate) struct FeatureConfig {
+    pub upscaled_resolution: UVec2,
+    pub optimal_render_resolution: UVec2,
+    pub min_render_resolution: UVec2,
+    pub max_render_resolution: UVec2,
+    pub perf_quality_mode: DlssPerfQualityMode,
+    pub feature_flags: DlssFeatureFlags,
+}
+
+impl FeatureConfig {
+    /// Query the render resolutions of `feature`, which must be the feature that will be created with this config.
+    pub fn new(
+        sdk: &DlssSdk,
+        feature: DlssFeature,
+        upscaled_resolution: UVec2,
+        perf_quality_mode: DlssPerfQualityMode,
+        feature_flags: DlssFeatureFlags,
+    ) -> Result<Self, DlssError> {
+        let mut config = Self {
+            upscaled_resolution,
+            optimal_render_resolution: upscaled_resolution,
+            min_render_resolution: upscaled_resolution,
+            max_render_resolution: upscaled_resolution,
+            perf_quality_mode,
+            feature_flags,
+        };
+        if perf_quality_mode == DlssPerfQualityMode::Dlaa {
+            return Ok(config);
+        }
+
+        let perf_quality_value = perf_quality_mode.as_perf_quality_value(upscaled_resolution);
+        let optimal = &mut config.optimal_render_resolution;
+        let max = &mut config.max_render_resolution;
+        let min = &mut config.min_render_resolution;
+        let mut deprecated_sharpness = 0.0f32;
+        let (entry_point, result) = unsafe {
+            match feature {
+                DlssFeature::SuperResolution => (
+                    "NGX_DLSS_GET_OPTIMAL_SETTINGS",
+                    NGX_DLSS_GET_OPTIMAL_SETTINGS(
+                        sdk.parameters,
+                        upscaled_resolution.x,
+                        upscaled_resolution.y,
+                        perf_quality_value,
+                        &mut optimal.x,
+                        &mut optimal.y,
+                        &mut max.x,
+                        &mut max.y,
+                        &mut min.x,
+                        &mut min.y,
+                        &mut deprecated_sharpness,
+                    ),
+                ),
+                DlssFeature::RayReconstruction => (
+                    "NGX_DLSSD_GET_OPTIMAL_SETTINGS",
+                    NGX_DLSSD_GET_OPTIMAL_SETTINGS(
+                        sdk.parameters,
+                        upscaled_resolution.x,
+                        upscaled_resolution.y,
+                        perf_quality_value,
+                        &mut optimal.x,
+                        &mut optimal.y,
+                        &mut max.x,
+                        &mut max.y,
+                        &mut min.x,
+                        &mut min.y,
+                        &mut deprecated_sharpness,
+                    ),
+                ),
+            }
+        };
+        check_ngx_result(entry_point, result)
+            .map_err(|e| e.with_context(config.error_context(None)))?;
+
+        Ok(config)
+    }
+
+    pub fn suggested_jitter(&self, frame_number: u32, render_resolution: UVec2) -> Vec2 {
+        let phase_count = jitter_phase_count(render_resolution, self.upscaled_resolution);
+        HaltonSequence::default().sample(frame_number % phase_count)
+    }
+
+    pub fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
+        (render_resolution.x as f32 / self.upscaled_resolution.x as f32).log2() - 1.0
+    }
+
     pub fn render_resolution_range(&self) -> RangeInclusive<UVec2> {
         self.min_render_resolution..=self.max_render_resolution
     }
+
+    /// The size of the rendered region of the input textures.
+    pub fn partial_texture_size(&self, render_parameters: &DlssRenderParameters) -> UVec2 {
+        render_parameters
+            .partial_texture_size
+            .unwrap_or(self.max_render_resolution)
+    }
+
+    /// The motion vector scale to give NGX, unless overridden by [`DlssRenderParameters::motion_vector_scale`].
+    pub fn motion_vector_scale(
+        &self,
+        render_parameters: &DlssRenderParameters,
+        convention: MotionVectorConvention,
+    ) -> Vec2 {
+        render_parameters
+            .motion_vector_scale
+            .unwrap_or_else(|| convention.scale(self.motion_vector_resolution(render_parameters)))
+    }
+
+    /// Size of the rendered region of the motion vector texture.
+    fn motion_vector_resolution(&self, render_parameters: &DlssRenderParameters) -> UVec2 {
+        if self
+            .feature_flags
+            .contains(DlssFeatureFlags::LowResolutionMotionVectors)
+        {
+            self.partial_texture_size(render_parameters)
+        } else {
+            self.upscaled_resolution
+        }
+    }
+
+    pub fn error_context(&self, render_resolution: Option<UVec2>) -> DlssErrorContext {
+        DlssErrorContext {
+            upscaled_resolution: Some(self.upscaled_resolution),
+            render_resolution,
+            perf_quality_mode: Some(self.perf_quality_mode),
+            feature_flags: Some(self.feature_flags),
+        }
+    }
 }
 
-impl Drop for DlssContext {
-    fn drop(&mut self) {
-        unsafe {
-            self.device.as_hal::<Vulkan, _, _>(|device| {
-                device
-                    .unwrap()
-                    .raw_device()
-                    .device_wait_idle()
-                    .expect("Failed to wait for idle device when destroying DlssContext");
+/// Owns the 1x1 texture used for [`DlssExposure::Value`].
+pub(crate) struct ExposureTexture {
+    texture: ScratchTexture,
+    /// The value last written to the texture.
+    value: Option<f32>,
+    queue: Queue,
+}
+
+impl ExposureTexture {
+    pub fn new(queue: &Queue) -> Self {
+        Self {
+            texture: ScratchTexture::new(
+                "dlss_exposure",
+                TextureFormat::R32Float,
+                TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+            ),
+            value: None,
+            queue: queue.clone(),
+        }
+    }
 
-                check_ngx_result(NVSDK_NGX_VULKAN_ReleaseFeature(self.feature))
-                    .expect("Failed to destroy DlssContext feature");
-            });
+    /// Upload the value of a [`DlssExposure::Value`], if any and if it changed.
+    ///
+    /// The value is written with [`Queue::write_texture`], so it takes effect at the next submission.
+    pub fn update(&mut self, exposure: &DlssExposure, device: &Device) {
+        let DlssExposure::Value(exposure) = *exposure else {
+            return;
+        };
+        if self.value.map(f32::to_bits) == Some(exposure.to_bits()) {
+            return;
+        }
+
+        self.texture.resize(UVec2::ONE, device);
+        self.queue.write_texture(
+            self.texture.texture().texture.as_image_copy(),
+            &exposure.to_ne_bytes(),
+            TexelCopyBufferLayout::default(),
+            Extent3d::default(),
+        );
+        self.value = Some(exposure);
+    }
+
+    /// Replace a [`DlssExposure::Value`] with this texture, which must have been written by [`Self::update`].
+    pub fn resolve<'a>(&'a self, exposure: DlssExposure<'a>) -> DlssExposure<'a> {
+        match exposure {
+            DlssExposure::Value(_) => DlssExposure::Manual {
+                exposure: self.texture.texture(),
+                exposure_scale: None,
+                pre_exposure: None,
+            },
+            exposure => exposure,
         }
     }
 }
 
-unsafe impl Send for DlssContext {}
-unsafe impl Sync for DlssContext {}
+/// Exposure inputs in the form NGX expects.
+pub(crate) struct ExposureResource {
+    pub texture: Option<NVSDK_NGX_Resource_VK>,
+    pub exposure_scale: f32,
+    pub pre_exposure: f32,
+}
+
+impl ExposureResource {
+    pub fn new(exposure: &DlssExposure, adapter: &Adapter) -> Self {
+        match exposure {
+            DlssExposure::Manual {
+                exposure,
+                exposure_scale,
+                pre_exposure,
+            } => Self {
+                texture: Some(exposure.as_resource(adapter)),
+                exposure_scale: exposure_scale.unwrap_or(1.0),
+                pre_exposure: pre_exposure.unwrap_or(0.0),
+            },
+            DlssExposure::Value(_) => unreachable!("resolved by ExposureTexture"),
+            DlssExposure::Automatic => Self {
+                texture: None,
+                exposure_scale: 0.0,
+                pre_exposure: 0.0,
+            },
+        }
+    }
+}
+
+/// [`DlssGuideTextures`] in the form NGX expects, kept alive until evaluation.
+pub(crate) struct GuideResources {
+    pub transparency_mask: Option<NVSDK_NGX_Resource_VK>,
+    pub particle_mask: Option<NVSDK_NGX_Resource_VK>,
+    pub animated_texture_mask: Option<NVSDK_NGX_Resource_VK>,
+    pub depth_high_res: Option<NVSDK_NGX_Resource_VK>,
+    pub position_view_space: Option<NVSDK_NGX_Resource_VK>,
+    pub motion_vectors_3d: Option<NVSDK_NGX_Resource_VK>,
+    pub ray_tracing_hit_distance: Option<NVSDK_NGX_Resource_VK>,
+    pub motion_vectors_reflections: Option<NVSDK_NGX_Resource_VK>,
+    pub gbuffer: [Option<NVSDK_NGX_Resource_VK>; GBUFFER_ATTRIBUTE_COUNT],
+}
+
+impl GuideResources {
+    pub fn new(guides: &DlssGuideTextures, adapter: &Adapter) -> Self {
+        let resource = |texture: &Option<DlssTexture>| {
+            texture.as_ref().map(|texture| texture.as_resource(adapter))
+        };
+
+        Self {
+            transparency_mask: resource(&guides.transparency_mask),
+            particle_mask: resource(&guides.particle_mask),
+            animated_texture_mask: resource(&guides.animated_texture_mask),
+            depth_high_res: resource(&guides.depth_high_res),
+            position_view_space: resource(&guides.position_view_space),
+            motion_vectors_3d: resource(&guides.motion_vectors_3d),
+            ray_tracing_hit_distance: resource(&guides.ray_tracing_hit_distance),
+            motion_vectors_reflections: resource(&guides.motion_vectors_reflections),
+            gbuffer: guides.gbuffer.each_ref().map(resource),
+        }
+    }
+}
 
-fn halton_sequence(mut index: u32, base: u32) -> f32 {
-    let mut f = 1.0;
-    let mut result = 0.0;
-    while index > 0 {
-        f /= base as f32;
-        result += f * (index % base) as f32;
-        index = (index as f32 / base as f32).floor() as u32;
+pub(crate) fn resource_ptr(
+    resource: &mut Option<NVSDK_NGX_Resource_VK>,
+) -> *mut NVSDK_NGX_Resource_VK {
+    resource
+        .as_mut()
+        .map_or(ptr::null_mut(), |resource| resource)
+}
+
+/// Time since the last call, unless overridden by `frame_time_delta`.
+pub(crate) fn frame_time_delta(
+    last_render: &mut Option<Instant>,
+    frame_time_delta: Option<Duration>,
+) -> Duration {
+    let now = Instant::now();
+    let measured = last_render.map_or(Duration::ZERO, |last_render| now - last_render);
+    *last_render = Some(now);
+    frame_time_delta.unwrap_or(measured)
+}
+
+/// Wait for the GPU to finish using a feature, and release it.
+pub(crate) fn release_feature(device: &Device, feature: *mut NVSDK_NGX_Handle, owner: &str) {
+    unsafe {
+        device.as_hal::<Vulkan, _, _>(|device| {
+            device
+                .unwrap()
+                .raw_device()
+                .device_wait_idle()
+                .unwrap_or_else(|_| {
+                    panic!("Failed to wait for idle device when destroying {owner}")
+                });
+
+            check_ngx_result(
+                "NVSDK_NGX_VULKAN_ReleaseFeature",
+                NVSDK_NGX_VULKAN_ReleaseFeature(feature),
+            )
+            .unwrap_or_else(|_| panic!("Failed to destroy {owner} feature"));
+        });
+    }
+}
+
+impl Drop for DlssContext {
+    fn drop(&mut self) {
+        release_feature(&self.device, self.feature, "DlssContext");
     }
-    result
 }
+
+unsafe impl Send for DlssContext {}
+unsafe impl Sync for DlssContext {}
@@ -0,0 +1,112 @@
+use crate::{
+    DlssError, DlssTexture,
+    compute_pass::{
+        ScratchTexture, create_compute_pipeline, create_shader_module, dispatch,
+        storage_texture_entry, texture_entry,
+    },
+};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindingResource, CommandEncoder, ComputePipeline, Device, TextureAspect, TextureFormat,
+    TextureSampleType, TextureUsages,
+};
+
+/// Compute pass that copies a depth buffer into an `R32Float` texture.
+///
+/// Useful when the depth buffer's format is not accepted by DLSS, see [`crate::DlssRenderParameters::supports_depth_format`].
+pub struct DlssDepthCopyPass {
+    pipeline: ComputePipeline,
+    bind_group_layout: BindGroupLayout,
+    output: ScratchTexture,
+}
+
+impl DlssDepthCopyPass {
+    pub fn new(device: &Device) -> Self {
+        let shader_module =
+            create_shader_module(device, "dlss_depth_copy", include_str!("depth_copy.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("dlss_depth_copy"),
+            entries: &[
+                texture_entry(0, TextureSampleType::Depth),
+                storage_texture_entry(1, TextureFormat::R32Float),
+            ],
+        });
+
+        let pipeline = create_compute_pipeline(
+            device,
+            "dlss_depth_copy",
+            &shader_module,
+            "copy_depth",
+            &bind_group_layout,
+        );
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            output: ScratchTexture::new(
+                "dlss_depth_copy_output",
+                TextureFormat::R32Float,
+                TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            ),
+        }
+    }
+
+    /// Encode commands to copy `depth` into an `R32Float` texture owned by this pass.
+    ///
+    /// `depth` must be a 2D view with [`wgpu::TextureAspect::DepthOnly`] if the texture has a combined depth-stencil format.
+    /// The stencil aspect is never accessed.
+    ///
+    /// The returned texture can be used as [`crate::DlssRenderParameters::depth`].
+    pub fn encode(
+        &mut self,
+        depth: &DlssTexture,
+        command_encoder: &mut CommandEncoder,
+        device: &Device,
+    ) -> Result<DlssTexture<'_>, DlssError> {
+        let depth_format = depth.format();
+        if !depth_format.has_depth_aspect() {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "DlssDepthCopyPass input must be a depth texture view, but has format {depth_format:?}"
+            )));
+        }
+        if depth.texture.format().is_combined_depth_stencil_format()
+            && depth.aspect() != TextureAspect::DepthOnly
+        {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "DlssDepthCopyPass input has combined depth-stencil format {:?}, but the view uses aspect {:?} instead of TextureAspect::DepthOnly",
+                depth.texture.format(),
+                depth.aspect()
+            )));
+        }
+
+        let size = depth.size();
+        self.output.resize(size, device);
+        let output = self.output.texture();
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("dlss_depth_copy"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(depth.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(output.view),
+                },
+            ],
+        });
+
+        dispatch(
+            command_encoder,
+            "dlss_depth_copy",
+            &self.pipeline,
+            &bind_group,
+            size,
+        );
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,12 @@
+@group(0) @binding(0) var depth: texture_depth_2d;
+@group(0) @binding(1) var output: texture_storage_2d<r32float, write>;
+
+@compute @workgroup_size(8, 8, 1)
+fn copy_depth(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if any(global_id.xy >= textureDimensions(output)) {
+        return;
+    }
+
+    let value = textureLoad(depth, global_id.xy, 0);
+    textureStore(output, global_id.xy, vec4(value, 0.0, 0.0, 0.0));
+}
@@ -0,0 +1,161 @@
+use crate::DlssFeatureFlags;
+use glam::{Mat4, UVec2, Vec3};
+use wgpu::TextureFormat;
+
+/// Inputs used by [`DlssFeatureFlags::infer`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DlssFeatureFlagInputs {
+    /// Format of [`crate::DlssRenderParameters::color`].
+    pub color_format: TextureFormat,
+    /// The camera's view-to-clip matrix, with or without jitter.
+    ///
+    /// Orthographic projections are assumed to be right-handed.
+    pub projection: Mat4,
+    /// Whether [`crate::DlssRenderParameters::motion_vectors`] include the camera jitter.
+    pub jittered_motion_vectors: bool,
+    /// Size of [`crate::DlssRenderParameters::motion_vectors`].
+    pub motion_vector_resolution: UVec2,
+    /// The resolution DLSS will output at.
+    pub upscaled_resolution: UVec2,
+}
+
+impl DlssFeatureFlags {
+    /// Derive the flags describing the given inputs.
+    ///
+    /// Only [`Self::HighDynamicRange`], [`Self::InvertedDepth`], [`Self::JitteredMotionVectors`] and
+    /// [`Self::LowResolutionMotionVectors`] are inferred. Other flags are settings, and should be added as needed.
+    pub fn infer(inputs: &DlssFeatureFlagInputs) -> Self {
+        let mut flags = Self::from_textures(
+            inputs.color_format,
+            inputs.motion_vector_resolution,
+            inputs.upscaled_resolution,
+        );
+        flags.set(Self::InvertedDepth, is_reverse_z(inputs.projection));
+        flags.set(Self::JitteredMotionVectors, inputs.jittered_motion_vectors);
+        flags
+    }
+
+    /// The subset of [`Self::infer`] that can be derived from textures alone, see [`Self::TEXTURE_INFERRED`].
+    pub(crate) fn from_textures(
+        color_format: TextureFormat,
+        motion_vector_resolution: UVec2,
+        upscaled_resolution: UVec2,
+    ) -> Self {
+        let mut flags = Self::empty();
+        flags.set(Self::HighDynamicRange, is_hdr_format(color_format));
+        flags.set(
+            Self::LowResolutionMotionVectors,
+            motion_vector_resolution.cmplt(upscaled_resolution).any(),
+        );
+        flags
+    }
+
+    /// Flags that [`crate::DlssContext`] can check against the textures passed to its first render.
+    pub(crate) const TEXTURE_INFERRED: Self =
+        Self::HighDynamicRange.union(Self::LowResolutionMotionVectors);
+}
+
+fn is_hdr_format(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::Rgba16Float
+            | TextureFormat::Rgba32Float
+            | TextureFormat::Rg11b10Ufloat
+            | TextureFormat::Rgb9e5Ufloat
+    )
+}
+
+fn is_reverse_z(projection: Mat4) -> bool {
+    // Points in front of a perspective camera have a positive clip space W
+    let w_z = projection.row(3).z;
+    let forward = if w_z != 0.0 { w_z.signum() } else { -1.0 };
+
+    let depth = |distance: f32| projection.project_point3(Vec3::Z * forward * distance).z;
+    depth(1.0) > depth(1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UPSCALED_RESOLUTION: UVec2 = UVec2::new(1920, 1080);
+
+    fn inputs(projection: Mat4) -> DlssFeatureFlagInputs {
+        DlssFeatureFlagInputs {
+            color_format: TextureFormat::Rgba8UnormSrgb,
+            projection,
+            jittered_motion_vectors: false,
+            motion_vector_resolution: UPSCALED_RESOLUTION,
+            upscaled_resolution: UPSCALED_RESOLUTION,
+        }
+    }
+
+    #[test]
+    fn detects_reverse_z() {
+        let cases = [
+            (Mat4::perspective_rh(1.0, 1.0, 0.1, 100.0), false),
+            (Mat4::perspective_lh(1.0, 1.0, 0.1, 100.0), false),
+            (Mat4::perspective_infinite_rh(1.0, 1.0, 0.1), false),
+            (Mat4::perspective_rh(1.0, 1.0, 100.0, 0.1), true),
+            (Mat4::perspective_infinite_reverse_rh(1.0, 1.0, 0.1), true),
+            (Mat4::perspective_infinite_reverse_lh(1.0, 1.0, 0.1), true),
+            (
+                Mat4::orthographic_rh(-1.0, 1.0, -1.0, 1.0, 0.1, 100.0),
+                false,
+            ),
+            (
+                Mat4::orthographic_rh(-1.0, 1.0, -1.0, 1.0, 100.0, 0.1),
+                true,
+            ),
+        ];
+
+        for (projection, reverse_z) in cases {
+            let flags = DlssFeatureFlags::infer(&inputs(projection));
+            assert_eq!(
+                flags.contains(DlssFeatureFlags::InvertedDepth),
+                reverse_z,
+                "{projection}"
+            );
+        }
+    }
+
+    #[test]
+    fn detects_hdr_color() {
+        let projection = Mat4::perspective_infinite_rh(1.0, 1.0, 0.1);
+        for (color_format, hdr) in [
+            (TextureFormat::Rgba8UnormSrgb, false),
+            (TextureFormat::Rgb10a2Unorm, false),
+            (TextureFormat::Rgba16Float, true),
+            (TextureFormat::Rg11b10Ufloat, true),
+        ] {
+            let flags = DlssFeatureFlags::infer(&DlssFeatureFlagInputs {
+                color_format,
+                ..inputs(projection)
+            });
+            assert_eq!(
+                flags.contains(DlssFeatureFlags::HighDynamicRange),
+                hdr,
+                "{color_format:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn detects_motion_vector_settings() {
+        let projection = Mat4::perspective_infinite_rh(1.0, 1.0, 0.1);
+        assert_eq!(
+            DlssFeatureFlags::infer(&inputs(projection)),
+            DlssFeatureFlags::empty()
+        );
+
+        let flags = DlssFeatureFlags::infer(&DlssFeatureFlagInputs {
+            jittered_motion_vectors: true,
+            motion_vector_resolution: UPSCALED_RESOLUTION / 2,
+            ..inputs(projection)
+        });
+        assert_eq!(
+            flags,
+            DlssFeatureFlags::JitteredMotionVectors | DlssFeatureFlags::LowResolutionMotionVectors
+        );
+    }
+}
@@ -6,7 +6,27 @@ use std::{
 };
 use uuid::Uuid;
 
-pub fn with_feature_info<F, T>(project_id: Uuid, callback: F) -> T
+/// The NGX features used by this crate.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DlssFeature {
+    /// DLSS Super Resolution, used by [`crate::DlssContext`].
+    SuperResolution,
+    /// DLSS Ray Reconstruction (DLSS-D), used by [`crate::DlssRayReconstructionContext`].
+    RayReconstruction,
+}
+
+impl DlssFeature {
+    pub const ALL: [Self; 2] = [Self::SuperResolution, Self::RayReconstruction];
+
+    fn feature_id(self) -> NVSDK_NGX_Feature {
+        match self {
+            Self::SuperResolution => NVSDK_NGX_Feature_NVSDK_NGX_Feature_SuperSampling,
+            Self::RayReconstruction => NVSDK_NGX_Feature_NVSDK_NGX_Feature_RayReconstruction,
+        }
+    }
+}
+
+pub fn with_feature_info<F, T>(project_id: Uuid, feature: DlssFeature, callback: F) -> T
 where
     F: FnOnce(&NVSDK_NGX_FeatureDiscoveryInfo) -> T,
 {
@@ -36,7 +56,7 @@ where
 
     let feature_info = NVSDK_NGX_FeatureDiscoveryInfo {
         SDKVersion: NVSDK_NGX_Version_NVSDK_NGX_Version_API,
-        FeatureID: NVSDK_NGX_Feature_NVSDK_NGX_Feature_SuperSampling,
+        FeatureID: feature.feature_id(),
         Identifier: NVSDK_NGX_Application_Identifier {
             IdentifierType: NVSDK_NGX_Application_Identifier_Type_NVSDK_NGX_Application_Identifier_Type_Project_Id,
             v: NVSDK_NGX_Application_Identifier_v {
@@ -0,0 +1,275 @@
+use crate::{
+    DlssDepthType, DlssError, DlssRayReconstructionGuideLayout, DlssRoughnessMode, DlssTexture,
+    compute_pass::{
+        ScratchTexture, UniformValue, create_compute_pipeline, create_shader_module,
+        create_uniform_buffer, dispatch, storage_texture_entry, texture_entry, uniform_entry,
+    },
+};
+use glam::{Mat4, Vec4};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindingResource, CommandEncoder, ComputePipeline, Device, TextureFormat, TextureSampleType,
+    TextureUsages,
+};
+
+/// How normals are stored in [`DlssGBufferInputs::normals`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum DlssNormalEncoding {
+    /// XYZ in the -1.0 to 1.0 range, e.g. in a float texture.
+    #[default]
+    Signed,
+    /// XYZ remapped to the 0.0 to 1.0 range, e.g. in an `Rgba8Unorm` texture.
+    Unsigned,
+    /// Octahedral encoding in XY, remapped to the 0.0 to 1.0 range.
+    Octahedral,
+}
+
+/// A typical G-buffer, as used by [`DlssGuidePackingPass`].
+///
+/// All textures must have the same size.
+pub struct DlssGBufferInputs<'a> {
+    /// Depth buffer, see [`crate::DlssRenderParameters::depth`].
+    pub depth: DlssTexture<'a>,
+    /// Linear base color in RGB.
+    pub base_color: DlssTexture<'a>,
+    /// World space normals.
+    pub normals: DlssTexture<'a>,
+    pub normal_encoding: DlssNormalEncoding,
+    /// Metallic and perceptual roughness, each in the channel given by [`Self::metallic_channel`] and [`Self::roughness_channel`].
+    pub metallic_roughness: DlssTexture<'a>,
+    /// Channel index of metallic, e.g. 2 for glTF's packing.
+    pub metallic_channel: u32,
+    /// Channel index of perceptual roughness, e.g. 1 for glTF's packing.
+    pub roughness_channel: u32,
+    /// The camera's world-to-view matrix.
+    pub world_to_view: Mat4,
+    /// The camera's view-to-clip matrix, without jitter.
+    pub view_to_clip: Mat4,
+}
+
+/// Guide buffers produced by [`DlssGuidePackingPass`], for use in [`crate::DlssRayReconstructionRenderParameters`].
+pub struct DlssRayReconstructionGuides<'a> {
+    pub diffuse_albedo: DlssTexture<'a>,
+    pub specular_albedo: DlssTexture<'a>,
+    /// Normalized world space normals, with linear roughness (perceptual roughness squared) in the alpha channel.
+    pub normals: DlssTexture<'a>,
+    /// Linear roughness, or `None` with [`DlssRoughnessMode::Packed`].
+    pub roughness: Option<DlssTexture<'a>>,
+    /// Linear depth to use as [`crate::DlssRenderParameters::depth`] with [`DlssDepthType::Linear`], otherwise `None`.
+    pub linear_depth: Option<DlssTexture<'a>>,
+}
+
+/// Compute pass that converts a typical G-buffer into the guide buffers expected by DLSS Ray Reconstruction.
+///
+/// Specular albedo is computed from F0 and the view angle using an analytical approximation of the environment BRDF.
+pub struct DlssGuidePackingPass {
+    material_pipeline: ComputePipeline,
+    material_bind_group_layout: BindGroupLayout,
+    depth_pipeline: ComputePipeline,
+    depth_bind_group_layout: BindGroupLayout,
+    diffuse_albedo: ScratchTexture,
+    specular_albedo: ScratchTexture,
+    normals: ScratchTexture,
+    roughness: ScratchTexture,
+    linear_depth: ScratchTexture,
+}
+
+impl DlssGuidePackingPass {
+    pub fn new(device: &Device) -> Self {
+        let shader_module = create_shader_module(
+            device,
+            "dlss_guide_packing",
+            include_str!("guide_packing.wgsl"),
+        );
+
+        let material_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("dlss_guide_packing_material"),
+                entries: &[
+                    uniform_entry(0),
+                    texture_entry(1, TextureSampleType::Depth),
+                    texture_entry(2, TextureSampleType::Float { filterable: false }),
+                    texture_entry(3, TextureSampleType::Float { filterable: false }),
+                    texture_entry(4, TextureSampleType::Float { filterable: false }),
+                    storage_texture_entry(5, TextureFormat::Rgba8Unorm),
+                    storage_texture_entry(6, TextureFormat::Rgba8Unorm),
+                    storage_texture_entry(7, TextureFormat::Rgba16Float),
+                    storage_texture_entry(8, TextureFormat::R32Float),
+                ],
+            });
+        let depth_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("dlss_guide_packing_depth"),
+            entries: &[
+                uniform_entry(0),
+                texture_entry(1, TextureSampleType::Depth),
+                storage_texture_entry(9, TextureFormat::R32Float),
+            ],
+        });
+
+        let output = |label, format| {
+            ScratchTexture::new(
+                label,
+                format,
+                TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            )
+        };
+
+        Self {
+            material_pipeline: create_compute_pipeline(
+                device,
+                "dlss_guide_packing_material",
+                &shader_module,
+                "pack_material_guides",
+                &material_bind_group_layout,
+            ),
+            material_bind_group_layout,
+            depth_pipeline: create_compute_pipeline(
+                device,
+                "dlss_guide_packing_depth",
+                &shader_module,
+                "linearize_depth",
+                &depth_bind_group_layout,
+            ),
+            depth_bind_group_layout,
+            diffuse_albedo: output("dlss_diffuse_albedo", TextureFormat::Rgba8Unorm),
+            specular_albedo: output("dlss_specular_albedo", TextureFormat::Rgba8Unorm),
+            normals: output("dlss_normals", TextureFormat::Rgba16Float),
+            roughness: output("dlss_roughness", TextureFormat::R32Float),
+            linear_depth: output("dlss_linear_depth", TextureFormat::R32Float),
+        }
+    }
+
+    /// Encode commands to convert `inputs` into guide buffers owned by this pass, in the given layout.
+    ///
+    /// Returns [`DlssError::InvalidRenderParameters`] if the textures differ in size, or a channel index is not a channel
+    /// of [`DlssGBufferInputs::metallic_roughness`].
+    pub fn encode(
+        &mut self,
+        inputs: &DlssGBufferInputs,
+        guide_layout: DlssRayReconstructionGuideLayout,
+        command_encoder: &mut CommandEncoder,
+        device: &Device,
+    ) -> Result<DlssRayReconstructionGuides<'_>, DlssError> {
+        let size = inputs.depth.size();
+        for (name, texture) in [
+            ("base_color", &inputs.base_color),
+            ("normals", &inputs.normals),
+            ("metallic_roughness", &inputs.metallic_roughness),
+        ] {
+            if texture.size() != size {
+                return Err(DlssError::InvalidRenderParameters(format!(
+                    "DlssGuidePackingPass {name} has size {}, but must be the same size as depth ({size})",
+                    texture.size()
+                )));
+            }
+        }
+        let format = inputs.metallic_roughness.format();
+        let channel_count = u32::from(format.components());
+        for (name, channel) in [
+            ("metallic_channel", inputs.metallic_channel),
+            ("roughness_channel", inputs.roughness_channel),
+        ] {
+            if channel >= channel_count {
+                return Err(DlssError::InvalidRenderParameters(format!(
+                    "DlssGuidePackingPass {name} is {channel}, but metallic_roughness format {format:?} only has {channel_count} channel(s)"
+                )));
+            }
+        }
+
+        for texture in [
+            &mut self.diffuse_albedo,
+            &mut self.specular_albedo,
+            &mut self.normals,
+            &mut self.roughness,
+        ] {
+            texture.resize(size, device);
+        }
+        let linear_depth = guide_layout.depth_type == DlssDepthType::Linear;
+        if linear_depth {
+            self.linear_depth.resize(size, device);
+        }
+
+        let normal_encoding = match inputs.normal_encoding {
+            DlssNormalEncoding::Signed => 0u32,
+            DlssNormalEncoding::Unsigned => 1,
+            DlssNormalEncoding::Octahedral => 2,
+        };
+        let orthographic = inputs.view_to_clip.row(3) == Vec4::W;
+        let uniforms = [
+            inputs.view_to_clip.inverse().uniform_words(),
+            inputs.world_to_view.uniform_words(),
+            normal_encoding.uniform_words(),
+            inputs.metallic_channel.uniform_words(),
+            inputs.roughness_channel.uniform_words(),
+            (orthographic as u32).uniform_words(),
+        ]
+        .concat();
+        let uniform_buffer = create_uniform_buffer(device, "dlss_guide_packing", &uniforms);
+
+        let outputs = DlssRayReconstructionGuides {
+            diffuse_albedo: self.diffuse_albedo.texture(),
+            specular_albedo: self.specular_albedo.texture(),
+            normals: self.normals.texture(),
+            roughness: (guide_layout.roughness_mode == DlssRoughnessMode::Unpacked)
+                .then(|| self.roughness.texture()),
+            linear_depth: linear_depth.then(|| self.linear_depth.texture()),
+        };
+
+        let material_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("dlss_guide_packing_material"),
+            layout: &self.material_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                view_entry(1, &inputs.depth),
+                view_entry(2, &inputs.base_color),
+                view_entry(3, &inputs.normals),
+                view_entry(4, &inputs.metallic_roughness),
+                view_entry(5, &outputs.diffuse_albedo),
+                view_entry(6, &outputs.specular_albedo),
+                view_entry(7, &outputs.normals),
+                view_entry(8, &self.roughness.texture()),
+            ],
+        });
+        dispatch(
+            command_encoder,
+            "dlss_guide_packing_material",
+            &self.material_pipeline,
+            &material_bind_group,
+            size,
+        );
+
+        if linear_depth {
+            let depth_bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("dlss_guide_packing_depth"),
+                layout: &self.depth_bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    view_entry(1, &inputs.depth),
+                    view_entry(9, &self.linear_depth.texture()),
+                ],
+            });
+            dispatch(
+                command_encoder,
+                "dlss_guide_packing_depth",
+                &self.depth_pipeline,
+                &depth_bind_group,
+                size,
+            );
+        }
+
+        Ok(outputs)
+    }
+}
+
+fn view_entry<'a>(binding: u32, texture: &DlssTexture<'a>) -> BindGroupEntry<'a> {
+    BindGroupEntry {
+        binding,
+        resource: BindingResource::TextureView(texture.view),
+    }
+}
@@ -0,0 +1,110 @@
+const NORMAL_ENCODING_SIGNED: u32 = 0u;
+const NORMAL_ENCODING_UNSIGNED: u32 = 1u;
+const NORMAL_ENCODING_OCTAHEDRAL: u32 = 2u;
+
+// Reflectance of dielectrics at normal incidence
+const DIELECTRIC_F0: f32 = 0.04;
+
+struct Uniforms {
+    clip_to_view: mat4x4<f32>,
+    world_to_view: mat4x4<f32>,
+    normal_encoding: u32,
+    metallic_channel: u32,
+    roughness_channel: u32,
+    orthographic: u32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var depth: texture_depth_2d;
+@group(0) @binding(2) var base_color: texture_2d<f32>;
+@group(0) @binding(3) var normals: texture_2d<f32>;
+@group(0) @binding(4) var metallic_roughness: texture_2d<f32>;
+@group(0) @binding(5) var diffuse_albedo_output: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(6) var specular_albedo_output: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(7) var normals_output: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(8) var roughness_output: texture_storage_2d<r32float, write>;
+// Only used by linearize_depth, which has its own bind group layout
+@group(0) @binding(9) var linear_depth_output: texture_storage_2d<r32float, write>;
+
+// Unprojected clip space position, before dividing by W
+fn view_position(pixel: vec2<u32>, size: vec2<u32>) -> vec4<f32> {
+    let uv = (vec2<f32>(pixel) + 0.5) / vec2<f32>(size);
+    let ndc = vec2(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    return uniforms.clip_to_view * vec4(ndc, textureLoad(depth, pixel, 0), 1.0);
+}
+
+fn decode_normal(encoded: vec4<f32>) -> vec3<f32> {
+    switch uniforms.normal_encoding {
+        case NORMAL_ENCODING_UNSIGNED: {
+            return normalize(encoded.xyz * 2.0 - 1.0);
+        }
+        case NORMAL_ENCODING_OCTAHEDRAL: {
+            let e = encoded.xy * 2.0 - 1.0;
+            var n = vec3(e, 1.0 - abs(e.x) - abs(e.y));
+            let t = max(-n.z, 0.0);
+            n.x += select(t, -t, n.x >= 0.0);
+            n.y += select(t, -t, n.y >= 0.0);
+            return normalize(n);
+        }
+        case NORMAL_ENCODING_SIGNED, default: {
+            return normalize(encoded.xyz);
+        }
+    }
+}
+
+// Analytical approximation of the split-sum environment BRDF, from "Physically Based Shading on Mobile" by Brian Karis
+fn specular_albedo(f0: vec3<f32>, roughness: f32, n_dot_v: f32) -> vec3<f32> {
+    let c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+    let c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+    let r = roughness * c0 + c1;
+    let a004 = min(r.x * r.x, exp2(-9.28 * n_dot_v)) * r.x + r.y;
+    let ab = vec2(-1.04, 1.04) * a004 + r.zw;
+    return f0 * ab.x + ab.y;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn pack_material_guides(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(normals_output);
+    if any(global_id.xy >= size) {
+        return;
+    }
+    let pixel = global_id.xy;
+
+    let color = textureLoad(base_color, pixel, 0).rgb;
+    let material = textureLoad(metallic_roughness, pixel, 0);
+    let metallic = material[uniforms.metallic_channel];
+    let perceptual_roughness = material[uniforms.roughness_channel];
+    // DLSS-RR expects linear roughness
+    let roughness = perceptual_roughness * perceptual_roughness;
+    let normal = decode_normal(textureLoad(normals, pixel, 0));
+
+    // Direction from the surface towards the camera, in view space
+    let position = view_position(pixel, size);
+    var to_camera: vec3<f32>;
+    if uniforms.orthographic != 0u {
+        to_camera = vec3(0.0, 0.0, -position.z);
+    } else {
+        // Positions at infinity have a W of zero, but still have a direction
+        to_camera = -position.xyz * select(sign(position.w), 1.0, position.w == 0.0);
+    }
+    let view_normal = normalize((uniforms.world_to_view * vec4(normal, 0.0)).xyz);
+    let n_dot_v = max(dot(view_normal, normalize(to_camera)), 1e-4);
+
+    let f0 = mix(vec3(DIELECTRIC_F0), color, metallic);
+    textureStore(diffuse_albedo_output, pixel, vec4(color * (1.0 - metallic), 1.0));
+    textureStore(specular_albedo_output, pixel, vec4(specular_albedo(f0, perceptual_roughness, n_dot_v), 1.0));
+    textureStore(normals_output, pixel, vec4(normal, roughness));
+    textureStore(roughness_output, pixel, vec4(roughness, 0.0, 0.0, 0.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn linearize_depth(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(linear_depth_output);
+    if any(global_id.xy >= size) {
+        return;
+    }
+
+    let position = view_position(global_id.xy, size);
+    let linear_depth = abs(position.z / position.w);
+    textureStore(linear_depth_output, global_id.xy, vec4(linear_depth, 0.0, 0.0, 0.0));
+}
@@ -0,0 +1,234 @@
+use crate::{
+    DlssError, DlssTexture,
+    compute_pass::{ScratchTexture, create_shader_module, is_output_format},
+};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Color, ColorTargetState, ColorWrites,
+    CommandEncoder, Device, Extent3d, FragmentState, LoadOp, MultisampleState, Operations,
+    Origin3d, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModule, ShaderStages, StoreOp, TexelCopyTextureInfo, TextureAspect, TextureFormat,
+    TextureSampleType, TextureUsages, TextureViewDimension, VertexState,
+};
+
+/// How [`IntermediateOutput`] writes its texture to the target.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransferMode {
+    /// Texture copy, with an intermediate texture of the target's format.
+    Copy,
+    /// Fullscreen draw into the target, with an `Rgba16Float` intermediate texture.
+    Blit,
+}
+
+/// Stands in for a [`crate::DlssRenderParameters::dlss_output`] that cannot be used as a storage texture,
+/// such as an sRGB swapchain texture, and transfers the result to it afterwards.
+pub(crate) struct IntermediateOutput {
+    mode: TransferMode,
+    texture: Option<(TextureFormat, ScratchTexture)>,
+    shader_module: ShaderModule,
+    bind_group_layout: BindGroupLayout,
+    /// Blit pipeline for the current target format.
+    pipeline: Option<(TextureFormat, RenderPipeline)>,
+}
+
+impl IntermediateOutput {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            mode: TransferMode::Blit,
+            texture: None,
+            shader_module: create_shader_module(
+                device,
+                "dlss_intermediate_output",
+                include_str!("intermediate_output.wgsl"),
+            ),
+            bind_group_layout: device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("dlss_intermediate_output"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            }),
+            pipeline: None,
+        }
+    }
+
+    /// Create the intermediate texture and pipeline needed for `target`, see [`transfer_mode`].
+    pub fn prepare(&mut self, target: &DlssTexture, device: &Device) -> Result<(), DlssError> {
+        let target_format = target.format();
+        let (mode, format) = transfer_mode(target)?;
+
+        self.mode = mode;
+        if self.texture.as_ref().is_none_or(|(f, _)| *f != format) {
+            self.texture = Some((
+                format,
+                ScratchTexture::new(
+                    "dlss_intermediate_output",
+                    format,
+                    TextureUsages::STORAGE_BINDING
+                        | TextureUsages::TEXTURE_BINDING
+                        | TextureUsages::COPY_SRC,
+                ),
+            ));
+        }
+        self.texture
+            .as_mut()
+            .unwrap()
+            .1
+            .resize(target.size(), device);
+
+        if mode == TransferMode::Blit
+            && self
+                .pipeline
+                .as_ref()
+                .is_none_or(|(format, _)| *format != target_format)
+        {
+            self.pipeline = Some((target_format, self.create_pipeline(target_format, device)));
+        }
+
+        Ok(())
+    }
+
+    /// The texture to write instead of the target, which must have been created by [`Self::prepare`].
+    pub fn texture(&self) -> DlssTexture<'_> {
+        self.texture
+            .as_ref()
+            .expect("IntermediateOutput::prepare must be called before use")
+            .1
+            .texture()
+    }
+
+    /// Encode commands to write [`Self::texture`] to the `target` given to [`Self::prepare`].
+    pub fn transfer(
+        &self,
+        target: &DlssTexture,
+        command_encoder: &mut CommandEncoder,
+        device: &Device,
+    ) {
+        let texture = self.texture();
+
+        match self.mode {
+            TransferMode::Copy => {
+                let selector = target.selector();
+                let size = target.size();
+                command_encoder.copy_texture_to_texture(
+                    texture.texture.as_image_copy(),
+                    TexelCopyTextureInfo {
+                        texture: target.texture,
+                        mip_level: selector.mips.start,
+                        origin: Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: selector.layers.start,
+                        },
+                        aspect: TextureAspect::All,
+                    },
+                    Extent3d {
+                        width: size.x,
+                        height: size.y,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+            TransferMode::Blit => {
+                let (_, pipeline) = self.pipeline.as_ref().unwrap();
+                let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("dlss_intermediate_output"),
+                    layout: &self.bind_group_layout,
+                    entries: &[BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(texture.view),
+                    }],
+                });
+
+                let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("dlss_intermediate_output"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: target.view,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Clear(Color::TRANSPARENT),
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(0, &bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+    }
+
+    fn create_pipeline(&self, target_format: TextureFormat, device: &Device) -> RenderPipeline {
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("dlss_intermediate_output"),
+            bind_group_layouts: &[&self.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("dlss_intermediate_output"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &self.shader_module,
+                entry_point: Some("fullscreen_vertex"),
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &self.shader_module,
+                entry_point: Some("blit"),
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: target_format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+}
+
+/// Check that `target` can be written through an [`IntermediateOutput`], and choose how.
+///
+/// Copies are preferred, as they are exact. Otherwise `target` must be usable as a render attachment.
+pub(crate) fn transfer_mode(
+    target: &DlssTexture,
+) -> Result<(TransferMode, TextureFormat), DlssError> {
+    let target_format = target.format();
+    let usage = target.texture.usage();
+
+    if usage.contains(TextureUsages::COPY_DST)
+        && target_format == target.texture.format()
+        && is_output_format(target_format)
+    {
+        Ok((TransferMode::Copy, target_format))
+    } else if usage.contains(TextureUsages::RENDER_ATTACHMENT) {
+        let selector = target.selector();
+        if selector.mips.len() != 1 || selector.layers.len() != 1 {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "dlss_output is not a storage texture, so its view must select a single mip level and array layer to be rendered to, but it selects mips {:?} and layers {:?}",
+                selector.mips, selector.layers
+            )));
+        }
+        Ok((TransferMode::Blit, TextureFormat::Rgba16Float))
+    } else {
+        Err(DlssError::InvalidRenderParameters(format!(
+            "dlss_output must have STORAGE_BINDING, RENDER_ATTACHMENT, or COPY_DST usage with a format of Rgba8Unorm, Rgba16Float or Rgba32Float, but has usage {usage:?} and format {target_format:?}"
+        )))
+    }
+}
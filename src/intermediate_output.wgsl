@@ -0,0 +1,14 @@
+@group(0) @binding(0) var input: texture_2d<f32>;
+
+// Triangle covering the whole viewport
+@vertex
+fn fullscreen_vertex(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2(f32(vertex_index >> 1u), f32(vertex_index & 1u)) * 2.0;
+    return vec4(uv * vec2(2.0, -2.0) + vec2(-1.0, 1.0), 0.0, 1.0);
+}
+
+// Format conversion, including sRGB encoding, is done by the render target
+@fragment
+fn blit(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(input, vec2<i32>(position.xy), 0);
+}
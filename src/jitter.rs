@@ -0,0 +1,340 @@
+use glam::{Mat4, UVec2, Vec2};
+
+/// A sequence of subpixel camera jitter offsets.
+///
+/// Implemented for [`HaltonSequence`], [`R2Sequence`], and any `Fn(u32) -> Vec2`.
+pub trait JitterSequence {
+    /// The jitter offset for the given sample index, in pixels, within `[-0.5, 0.5)` on each axis.
+    fn sample(&self, index: u32) -> Vec2;
+}
+
+impl<F: Fn(u32) -> Vec2> JitterSequence for F {
+    fn sample(&self, index: u32) -> Vec2 {
+        self(index)
+    }
+}
+
+/// Halton low-discrepancy sequence.
+///
+/// Defaults to the Halton(2, 3) sequence recommended by the DLSS programming guide.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct HaltonSequence {
+    pub base_x: u32,
+    pub base_y: u32,
+}
+
+impl Default for HaltonSequence {
+    fn default() -> Self {
+        Self {
+            base_x: 2,
+            base_y: 3,
+        }
+    }
+}
+
+impl JitterSequence for HaltonSequence {
+    fn sample(&self, index: u32) -> Vec2 {
+        Vec2 {
+            x: radical_inverse(index, self.base_x),
+            y: radical_inverse(index, self.base_y),
+        } - 0.5
+    }
+}
+
+/// R2 low-discrepancy sequence, based on the plastic number.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct R2Sequence;
+
+impl JitterSequence for R2Sequence {
+    fn sample(&self, index: u32) -> Vec2 {
+        const PLASTIC_NUMBER: f64 = 1.324_717_957_244_746;
+        const ALPHA_X: f64 = 1.0 / PLASTIC_NUMBER;
+        const ALPHA_Y: f64 = 1.0 / (PLASTIC_NUMBER * PLASTIC_NUMBER);
+
+        let index = index as f64;
+        Vec2 {
+            x: (0.5 + ALPHA_X * index).fract() as f32,
+            y: (0.5 + ALPHA_Y * index).fract() as f32,
+        } - 0.5
+    }
+}
+
+/// Number of jitter phases DLSS needs to cover every output pixel, based on the upscaling ratio of both axes.
+pub fn jitter_phase_count(render_resolution: UVec2, upscaled_resolution: UVec2) -> u32 {
+    let ratio = upscaled_resolution.as_vec2() / render_resolution.max(UVec2::ONE).as_vec2();
+    ((8.0 * ratio.x * ratio.y) as u32).max(1)
+}
+
+/// A projection matrix with subpixel jitter applied, returned by [`jitter_projection`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct JitteredProjection {
+    /// The jittered projection matrix, to render the camera with.
+    pub projection: Mat4,
+    /// The matching value for [`crate::DlssRenderParameters::jitter_offset`].
+    pub jitter_offset: Vec2,
+}
+
+/// Apply a subpixel jitter to a projection matrix.
+///
+/// `jitter` is in pixels, with X pointing right and Y pointing down, e.g. from [`crate::DlssContext::suggested_jitter`]
+/// or [`JitterGenerator::next_jitter`]. The projection may be perspective (including reverse-Z and infinite far plane)
+/// or orthographic, and must map to wgpu's clip space.
+pub fn jitter_projection(
+    projection: Mat4,
+    jitter: Vec2,
+    render_resolution: UVec2,
+) -> JitteredProjection {
+    let ndc_offset = jitter * Vec2::new(2.0, -2.0) / render_resolution.as_vec2();
+
+    // Offsetting clip space XY by the NDC offset times W shifts the image after the perspective divide,
+    // regardless of the kind of projection
+    JitteredProjection {
+        projection: Mat4::from_translation(ndc_offset.extend(0.0)) * projection,
+        jitter_offset: jitter,
+    }
+}
+
+/// Recover the subpixel jitter applied by [`jitter_projection`], given the original projection matrix.
+///
+/// The result is the matching value for [`crate::DlssRenderParameters::jitter_offset`].
+pub fn projection_jitter_offset(
+    jittered_projection: Mat4,
+    projection: Mat4,
+    render_resolution: UVec2,
+) -> Vec2 {
+    let w_row = projection.row(3);
+    let difference = jittered_projection - projection;
+    let ndc_offset = Vec2::new(difference.row(0).dot(w_row), difference.row(1).dot(w_row))
+        / w_row.length_squared();
+
+    ndc_offset * render_resolution.as_vec2() / Vec2::new(2.0, -2.0)
+}
+
+/// Stateful generator producing one jitter offset per frame from a [`JitterSequence`].
+#[derive(Clone, Debug)]
+pub struct JitterGenerator<S = HaltonSequence> {
+    sequence: S,
+    frame_index: u32,
+    phase_count: u32,
+}
+
+impl<S: JitterSequence> JitterGenerator<S> {
+    pub fn new(sequence: S) -> Self {
+        Self {
+            sequence,
+            frame_index: 0,
+            phase_count: 1,
+        }
+    }
+
+    /// Returns the jitter offset for the current frame, and advances to the next frame.
+    ///
+    /// The sequence repeats every [`jitter_phase_count`] frames.
+    pub fn next_jitter(&mut self, render_resolution: UVec2, upscaled_resolution: UVec2) -> Vec2 {
+        self.phase_count = jitter_phase_count(render_resolution, upscaled_resolution);
+        let jitter = self.sequence.sample(self.frame_index % self.phase_count);
+        self.frame_index = self.frame_index.wrapping_add(1);
+        jitter
+    }
+
+    /// Restart the sequence from the first sample.
+    pub fn reset(&mut self) {
+        self.frame_index = 0;
+    }
+
+    /// Number of frames generated since creation or the last [`Self::reset`].
+    pub fn frame_index(&self) -> u32 {
+        self.frame_index
+    }
+
+    /// Phase count used for the most recent call to [`Self::next_jitter`].
+    pub fn phase_count(&self) -> u32 {
+        self.phase_count
+    }
+
+    pub fn sequence(&self) -> &S {
+        &self.sequence
+    }
+}
+
+impl<S: JitterSequence + Default> Default for JitterGenerator<S> {
+    fn default() -> Self {
+        Self::new(S::default())
+    }
+}
+
+fn radical_inverse(mut index: u32, base: u32) -> f32 {
+    let mut f = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        f /= base as f32;
+        result += f * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    fn assert_well_distributed(sequence: impl JitterSequence, sample_count: u32) {
+        let samples = (0..sample_count)
+            .map(|i| sequence.sample(i))
+            .collect::<Vec<_>>();
+
+        for sample in &samples {
+            assert!(sample.cmpge(Vec2::splat(-0.5)).all() && sample.cmplt(Vec2::splat(0.5)).all());
+        }
+
+        let mean = samples.iter().sum::<Vec2>() / sample_count as f32;
+        assert!(
+            mean.abs().max_element() < 0.05,
+            "mean {mean} is not centered"
+        );
+
+        // Every cell of a 4x4 grid should receive its share of samples
+        let mut cells = [0u32; 16];
+        for sample in &samples {
+            let cell = ((*sample + 0.5) * 4.0).as_uvec2().min(UVec2::splat(3));
+            cells[(cell.y * 4 + cell.x) as usize] += 1;
+        }
+        let expected = sample_count / 16;
+        for count in cells {
+            assert!(
+                count.abs_diff(expected) <= expected / 4 + 1,
+                "cell count {count} deviates from {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn halton_matches_reference_values() {
+        let sequence = HaltonSequence::default();
+        assert_eq!(sequence.sample(0), Vec2::splat(-0.5));
+        assert_eq!(sequence.sample(1), Vec2::new(0.5, 1.0 / 3.0) - 0.5);
+        assert_eq!(sequence.sample(2), Vec2::new(0.25, 2.0 / 3.0) - 0.5);
+        assert_eq!(sequence.sample(3), Vec2::new(0.75, 1.0 / 9.0) - 0.5);
+    }
+
+    #[test]
+    fn halton_handles_large_indices() {
+        // 2^25 + 3 is not exactly representable as an f32, so dividing it as one drops digits
+        let index = (1 << 25) + 3;
+        assert!((radical_inverse(index, 2) - 0.75).abs() < 1e-6);
+        assert!(radical_inverse(u32::MAX, 3) < 1.0);
+    }
+
+    #[test]
+    fn halton_is_well_distributed() {
+        assert_well_distributed(HaltonSequence::default(), 256);
+    }
+
+    #[test]
+    fn r2_is_well_distributed() {
+        assert_well_distributed(R2Sequence, 256);
+    }
+
+    #[test]
+    fn closures_are_sequences() {
+        let sequence = |index: u32| Vec2::splat(index as f32 * 0.1 - 0.5);
+        assert_eq!(JitterSequence::sample(&sequence, 2), Vec2::splat(-0.3));
+    }
+
+    #[test]
+    fn phase_count_uses_both_axes() {
+        assert_eq!(
+            jitter_phase_count(UVec2::new(1920, 1080), UVec2::new(1920, 1080)),
+            8
+        );
+        assert_eq!(
+            jitter_phase_count(UVec2::new(960, 540), UVec2::new(1920, 1080)),
+            32
+        );
+        assert_eq!(
+            jitter_phase_count(UVec2::new(960, 1080), UVec2::new(1920, 1080)),
+            16
+        );
+        assert_eq!(jitter_phase_count(UVec2::ZERO, UVec2::ZERO), 1);
+    }
+
+    fn pixel_position(projection: Mat4, point: Vec3, render_resolution: UVec2) -> Vec2 {
+        let ndc = projection.project_point3(point).truncate();
+        (ndc * Vec2::new(0.5, -0.5) + 0.5) * render_resolution.as_vec2()
+    }
+
+    fn assert_projection_round_trips(projection: Mat4) {
+        let render_resolution = UVec2::new(1280, 720);
+        let point = Vec3::new(0.7, -0.4, -5.0);
+
+        for jitter in [
+            Vec2::new(0.25, -0.125),
+            Vec2::new(-0.5, 0.4),
+            HaltonSequence::default().sample(5),
+        ] {
+            let jittered = jitter_projection(projection, jitter, render_resolution);
+            assert_eq!(jittered.jitter_offset, jitter);
+
+            let shift = pixel_position(jittered.projection, point, render_resolution)
+                - pixel_position(projection, point, render_resolution);
+            assert!(
+                shift.abs_diff_eq(jitter, 1e-3),
+                "image shifted by {shift} instead of {jitter}"
+            );
+
+            let recovered =
+                projection_jitter_offset(jittered.projection, projection, render_resolution);
+            assert!(recovered.abs_diff_eq(jitter, 1e-4));
+
+            // Depth must be unaffected by jitter
+            let depth = |projection: Mat4| projection.project_point3(point).z;
+            assert!((depth(jittered.projection) - depth(projection)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn perspective_projection_round_trips() {
+        assert_projection_round_trips(Mat4::perspective_rh(1.0, 16.0 / 9.0, 0.1, 100.0));
+    }
+
+    #[test]
+    fn reverse_z_projection_round_trips() {
+        assert_projection_round_trips(Mat4::perspective_infinite_reverse_rh(1.0, 16.0 / 9.0, 0.1));
+    }
+
+    #[test]
+    fn orthographic_projection_round_trips() {
+        assert_projection_round_trips(Mat4::orthographic_rh(-8.0, 8.0, -4.5, 4.5, 0.1, 100.0));
+    }
+
+    #[test]
+    fn generator_is_deterministic_and_repeats() {
+        let render_resolution = UVec2::new(960, 540);
+        let upscaled_resolution = UVec2::new(1920, 1080);
+
+        let mut a = JitterGenerator::<HaltonSequence>::default();
+        let mut b = JitterGenerator::<HaltonSequence>::default();
+        let first_cycle = (0..32)
+            .map(|_| a.next_jitter(render_resolution, upscaled_resolution))
+            .collect::<Vec<_>>();
+        let other_cycle = (0..32)
+            .map(|_| b.next_jitter(render_resolution, upscaled_resolution))
+            .collect::<Vec<_>>();
+        assert_eq!(first_cycle, other_cycle);
+        assert_eq!(a.phase_count(), 32);
+        assert_eq!(a.frame_index(), 32);
+
+        let second_cycle = (0..32)
+            .map(|_| a.next_jitter(render_resolution, upscaled_resolution))
+            .collect::<Vec<_>>();
+        assert_eq!(first_cycle, second_cycle);
+
+        a.reset();
+        assert_eq!(a.frame_index(), 0);
+        assert_eq!(
+            a.next_jitter(render_resolution, upscaled_resolution),
+            first_cycle[0]
+        );
+    }
+}
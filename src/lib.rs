@@ -44,20 +44,70 @@
 //! camera.mip_bias = context.suggested_mip_bias(camera.view_size);
 //!
 //! // Encode DLSS render commands
-//! let render_parameters = DlssRenderParameters { ... };
+//! let render_parameters = DlssRenderParameters::new(color, depth, motion_vectors, exposure, dlss_output);
 //! context.render(render_parameters, &mut command_encoder, &adapter)
 //!     .expect("Failed to render DLSS");
 //! ```
 
+mod auto_exposure;
+mod camera;
+mod compute_pass;
 mod context;
+mod depth_copy;
+mod feature_flags;
 mod feature_info;
+mod guide_packing;
+mod intermediate_output;
+mod jitter;
+mod motion_vector_dilation;
+mod motion_vectors;
+mod msaa_resolve;
 mod nvsdk_ngx;
+mod ray_reconstruction;
 mod render_parameters;
 mod request_device;
 mod sdk;
+mod sharpening;
+mod spatial_upscaler;
+mod stereo_context;
+mod temporal_upscaler;
+#[cfg(test)]
+mod test_utils;
+mod upscaler;
 
-pub use context::DlssContext;
-pub use nvsdk_ngx::{DlssError, DlssFeatureFlags, DlssPerfQualityMode};
-pub use render_parameters::{DlssExposure, DlssRenderParameters, DlssTexture};
+pub use auto_exposure::{DlssAutoExposurePass, DlssAutoExposureSettings, DlssExposureMetering};
+pub use camera::{DlssCamera, DlssCameraCutThresholds};
+pub use context::{DlssContext, DlssDebugOptions, DlssOwnedOutput, DlssOwnedOutputDescriptor};
+pub use depth_copy::DlssDepthCopyPass;
+pub use feature_flags::DlssFeatureFlagInputs;
+pub use guide_packing::{
+    DlssGBufferInputs, DlssGuidePackingPass, DlssNormalEncoding, DlssRayReconstructionGuides,
+};
+pub use jitter::{
+    HaltonSequence, JitterGenerator, JitterSequence, JitteredProjection, R2Sequence,
+    jitter_phase_count, jitter_projection, projection_jitter_offset,
+};
+pub use motion_vector_dilation::DlssMotionVectorDilationPass;
+pub use motion_vectors::{
+    DlssMotionVectorInputs, DlssMotionVectorPass, MotionVectorConvention, MotionVectorDirection,
+    MotionVectorUnits, MotionVectorYAxis,
+};
+pub use msaa_resolve::{
+    DlssDepthResolve, DlssMsaaResolveInputs, DlssMsaaResolveOutputs, DlssMsaaResolvePass,
+};
+pub use nvsdk_ngx::{
+    DlssError, DlssErrorContext, DlssFeatureFlags, DlssGBufferAttribute, DlssPerfQualityMode,
+    DlssToneMapperType, NgxError,
+};
+pub use ray_reconstruction::{
+    DlssDepthType, DlssRayReconstructionContext, DlssRayReconstructionGuideLayout,
+    DlssRayReconstructionRenderParameters, DlssRoughnessMode,
+};
+pub use render_parameters::{DlssExposure, DlssGuideTextures, DlssRenderParameters, DlssTexture};
 pub use request_device::{RequestDeviceError, request_device};
 pub use sdk::DlssSdk;
+pub use sharpening::DlssSharpeningPass;
+pub use spatial_upscaler::SpatialUpscaler;
+pub use stereo_context::DlssStereoContext;
+pub use temporal_upscaler::TemporalUpscaler;
+pub use upscaler::Upscaler;
@@ -0,0 +1,178 @@
+use crate::{
+    DlssError, DlssTexture,
+    compute_pass::{
+        ScratchTexture, UniformValue, create_compute_pipeline, create_shader_module,
+        create_uniform_buffer, dispatch, storage_texture_entry, texture_entry, uniform_entry,
+    },
+};
+use glam::UVec2;
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindingResource, CommandEncoder, ComputePipeline, Device, TextureFormat, TextureSampleType,
+    TextureUsages,
+};
+
+/// Compute pass that replaces each motion vector with that of the closest-depth pixel in its 3x3 neighborhood.
+///
+/// Reduces ghosting of thin geometry and edges when motion vectors are rendered at render resolution.
+/// See [`crate::DlssContext::set_motion_vector_dilation`] to run this pass as part of DLSS.
+pub struct DlssMotionVectorDilationPass {
+    pipeline: ComputePipeline,
+    bind_group_layout: BindGroupLayout,
+    /// For depth in a color format, such as `R32Float`.
+    float_depth_pipeline: ComputePipeline,
+    float_depth_bind_group_layout: BindGroupLayout,
+    output: ScratchTexture,
+}
+
+impl DlssMotionVectorDilationPass {
+    pub fn new(device: &Device) -> Self {
+        let shader_module = create_shader_module(
+            device,
+            "dlss_motion_vector_dilation",
+            include_str!("motion_vector_dilation.wgsl"),
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("dlss_motion_vector_dilation"),
+            entries: &[
+                uniform_entry(0),
+                texture_entry(1, TextureSampleType::Depth),
+                texture_entry(2, TextureSampleType::Float { filterable: false }),
+                storage_texture_entry(3, TextureFormat::Rg32Float),
+            ],
+        });
+        let float_depth_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("dlss_motion_vector_dilation_float_depth"),
+                entries: &[
+                    uniform_entry(0),
+                    texture_entry(2, TextureSampleType::Float { filterable: false }),
+                    storage_texture_entry(3, TextureFormat::Rg32Float),
+                    texture_entry(4, TextureSampleType::Float { filterable: false }),
+                ],
+            });
+
+        Self {
+            pipeline: create_compute_pipeline(
+                device,
+                "dlss_motion_vector_dilation",
+                &shader_module,
+                "dilate",
+                &bind_group_layout,
+            ),
+            bind_group_layout,
+            float_depth_pipeline: create_compute_pipeline(
+                device,
+                "dlss_motion_vector_dilation_float_depth",
+                &shader_module,
+                "dilate_float_depth",
+                &float_depth_bind_group_layout,
+            ),
+            float_depth_bind_group_layout,
+            output: ScratchTexture::new(
+                "dlss_motion_vector_dilation_output",
+                TextureFormat::Rg32Float,
+                TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            ),
+        }
+    }
+
+    /// Encode commands to write dilated motion vectors into an `Rg32Float` texture owned by this pass.
+    ///
+    /// `motion_vectors` must be the same size as `depth`, and keep their convention. `inverted_depth` is whether depth is
+    /// reversed (1.0 is closest), see [`crate::DlssFeatureFlags::InvertedDepth`]. If set, only the region of
+    /// `partial_texture_size` is read and written, see [`crate::DlssRenderParameters::partial_texture_size`].
+    ///
+    /// Returns [`DlssError::InvalidRenderParameters`] if `motion_vectors` and `depth` differ in size.
+    pub fn encode(
+        &mut self,
+        depth: &DlssTexture,
+        motion_vectors: &DlssTexture,
+        inverted_depth: bool,
+        partial_texture_size: Option<UVec2>,
+        command_encoder: &mut CommandEncoder,
+        device: &Device,
+    ) -> Result<DlssTexture<'_>, DlssError> {
+        let texture_size = depth.size();
+        if motion_vectors.size() != texture_size {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "DlssMotionVectorDilationPass motion_vectors has size {}, but must be the same size as depth ({texture_size})",
+                motion_vectors.size()
+            )));
+        }
+
+        self.output.resize(texture_size, device);
+        let output = self.output.texture();
+
+        let size = partial_texture_size.unwrap_or(texture_size);
+        let uniforms = [
+            size.uniform_words(),
+            (inverted_depth as u32).uniform_words(),
+            // Padding
+            0u32.uniform_words(),
+        ]
+        .concat();
+        let uniform_buffer =
+            create_uniform_buffer(device, "dlss_motion_vector_dilation", &uniforms);
+
+        let uniform_entry = BindGroupEntry {
+            binding: 0,
+            resource: uniform_buffer.as_entire_binding(),
+        };
+        let motion_vectors_entry = BindGroupEntry {
+            binding: 2,
+            resource: BindingResource::TextureView(motion_vectors.view),
+        };
+        let output_entry = BindGroupEntry {
+            binding: 3,
+            resource: BindingResource::TextureView(output.view),
+        };
+        let (pipeline, bind_group) = if depth.format().has_depth_aspect() {
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("dlss_motion_vector_dilation"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    uniform_entry,
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(depth.view),
+                    },
+                    motion_vectors_entry,
+                    output_entry,
+                ],
+            });
+            (&self.pipeline, bind_group)
+        } else {
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("dlss_motion_vector_dilation_float_depth"),
+                layout: &self.float_depth_bind_group_layout,
+                entries: &[
+                    uniform_entry,
+                    motion_vectors_entry,
+                    output_entry,
+                    BindGroupEntry {
+                        binding: 4,
+                        resource: BindingResource::TextureView(depth.view),
+                    },
+                ],
+            });
+            (&self.float_depth_pipeline, bind_group)
+        };
+
+        dispatch(
+            command_encoder,
+            "dlss_motion_vector_dilation",
+            pipeline,
+            &bind_group,
+            size,
+        );
+
+        Ok(output)
+    }
+
+    /// The texture written by the last call to [`Self::encode`].
+    pub(crate) fn output(&self) -> DlssTexture<'_> {
+        self.output.texture()
+    }
+}
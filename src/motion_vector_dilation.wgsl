@@ -0,0 +1,60 @@
+struct Uniforms {
+    // Size of the region of the input textures to read
+    size: vec2<u32>,
+    inverted_depth: u32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var depth: texture_depth_2d;
+@group(0) @binding(2) var motion_vectors: texture_2d<f32>;
+@group(0) @binding(3) var output: texture_storage_2d<rg32float, write>;
+@group(0) @binding(4) var float_depth: texture_2d<f32>;
+
+fn neighbor(pixel: vec2<i32>, offset: vec2<i32>) -> vec2<i32> {
+    return clamp(pixel + offset, vec2(0), vec2<i32>(uniforms.size) - 1);
+}
+
+// Write the motion vector of the closest of the 3x3 neighbors, whose depths are given in row-major order
+fn store_dilated(pixel: vec2<i32>, neighbor_depths: array<f32, 9>) {
+    var depths = neighbor_depths;
+    // Prefer the center pixel when depths are equal
+    var closest = 4;
+    for (var i = 0; i < 9; i++) {
+        let closer = select(depths[i] < depths[closest], depths[i] > depths[closest], uniforms.inverted_depth != 0u);
+        if closer {
+            closest = i;
+        }
+    }
+
+    let offset = vec2(closest % 3 - 1, closest / 3 - 1);
+    let motion_vector = textureLoad(motion_vectors, neighbor(pixel, offset), 0).xy;
+    textureStore(output, pixel, vec4(motion_vector, 0.0, 0.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn dilate(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if any(global_id.xy >= uniforms.size) {
+        return;
+    }
+    let pixel = vec2<i32>(global_id.xy);
+
+    var depths: array<f32, 9>;
+    for (var i = 0; i < 9; i++) {
+        depths[i] = textureLoad(depth, neighbor(pixel, vec2(i % 3 - 1, i / 3 - 1)), 0);
+    }
+    store_dilated(pixel, depths);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn dilate_float_depth(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if any(global_id.xy >= uniforms.size) {
+        return;
+    }
+    let pixel = vec2<i32>(global_id.xy);
+
+    var depths: array<f32, 9>;
+    for (var i = 0; i < 9; i++) {
+        depths[i] = textureLoad(float_depth, neighbor(pixel, vec2(i % 3 - 1, i / 3 - 1)), 0).r;
+    }
+    store_dilated(pixel, depths);
+}
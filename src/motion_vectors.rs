@@ -0,0 +1,361 @@
+use crate::{
+    DlssCamera, DlssError, DlssTexture, JitterSequence,
+    compute_pass::{
+        ScratchTexture, UniformValue, create_compute_pipeline, create_shader_module,
+        create_uniform_buffer, dispatch, storage_texture_entry, texture_entry, uniform_entry,
+    },
+};
+use glam::{Mat4, UVec2, Vec2};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindingResource, CommandEncoder, ComputePipeline, Device, TextureFormat, TextureSampleType,
+    TextureUsages,
+};
+
+/// Inputs for [`DlssMotionVectorPass::encode`].
+#[derive(Clone, Copy)]
+pub struct DlssMotionVectorInputs<'a> {
+    /// Depth buffer rendered with the current frame's jittered projection.
+    ///
+    /// Must be a 2D view with [`wgpu::TextureAspect::DepthOnly`] if the texture has a combined depth-stencil format.
+    pub depth: DlssTexture<'a>,
+    /// The current frame's world-to-clip matrix, without jitter.
+    pub view_projection: Mat4,
+    /// The previous frame's world-to-clip matrix, without jitter.
+    pub previous_view_projection: Mat4,
+    /// The current frame's jitter, see [`crate::DlssRenderParameters::jitter_offset`].
+    pub jitter_offset: Vec2,
+    /// Optional per-object velocity texture, the same size as `depth`, containing the full motion (camera and object) of
+    /// dynamic objects in its first two channels.
+    ///
+    /// The third channel is a coverage mask: 1.0 where velocity was written, and 0.0 (the clear value) elsewhere, where
+    /// camera motion is used instead. This keeps objects that are still on screen, such as a camera-attached weapon,
+    /// from being given camera motion. The format must therefore have at least three channels, e.g. `Rgba16Float`.
+    pub object_velocity: Option<DlssTexture<'a>>,
+    /// Multiplied with `object_velocity` to convert it to the output's convention: pixels at render resolution,
+    /// pointing from the current frame to the previous frame, with Y pointing down.
+    pub object_velocity_scale: Vec2,
+}
+
+impl<'a> DlssMotionVectorInputs<'a> {
+    /// Fill in the matrices and jitter from a [`DlssCamera`] for the current frame.
+    pub fn from_camera<S: JitterSequence>(camera: &DlssCamera<S>, depth: DlssTexture<'a>) -> Self {
+        Self {
+            depth,
+            view_projection: camera.view_projection(),
+            previous_view_projection: camera.previous_view_projection(),
+            jitter_offset: camera.jitter_offset(),
+            object_velocity: None,
+            object_velocity_scale: Vec2::ONE,
+        }
+    }
+}
+
+/// Compute pass that reconstructs camera motion vectors from a depth buffer.
+///
+/// Only correct for static geometry. Motion of dynamic objects can be merged in via
+/// [`DlssMotionVectorInputs::object_velocity`].
+pub struct DlssMotionVectorPass {
+    camera_pipeline: ComputePipeline,
+    camera_bind_group_layout: BindGroupLayout,
+    merge_pipeline: ComputePipeline,
+    merge_bind_group_layout: BindGroupLayout,
+    output: ScratchTexture,
+}
+
+impl DlssMotionVectorPass {
+    pub fn new(device: &Device) -> Self {
+        let shader_module = create_shader_module(
+            device,
+            "dlss_motion_vectors",
+            include_str!("motion_vectors.wgsl"),
+        );
+
+        let camera_entries = [
+            uniform_entry(0),
+            texture_entry(1, TextureSampleType::Depth),
+            storage_texture_entry(2, TextureFormat::Rg32Float),
+        ];
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("dlss_camera_motion_vectors"),
+                entries: &camera_entries,
+            });
+        let merge_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("dlss_merge_motion_vectors"),
+            entries: &[
+                camera_entries[0],
+                camera_entries[1],
+                camera_entries[2],
+                texture_entry(3, TextureSampleType::Float { filterable: false }),
+            ],
+        });
+
+        Self {
+            camera_pipeline: create_compute_pipeline(
+                device,
+                "dlss_camera_motion_vectors",
+                &shader_module,
+                "camera_motion_vectors",
+                &camera_bind_group_layout,
+            ),
+            camera_bind_group_layout,
+            merge_pipeline: create_compute_pipeline(
+                device,
+                "dlss_merge_motion_vectors",
+                &shader_module,
+                "merge_motion_vectors",
+                &merge_bind_group_layout,
+            ),
+            merge_bind_group_layout,
+            output: ScratchTexture::new(
+                "dlss_motion_vectors_output",
+                TextureFormat::Rg32Float,
+                TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            ),
+        }
+    }
+
+    /// Encode commands to write motion vectors into an `Rg32Float` texture owned by this pass.
+    ///
+    /// The returned texture is the same size as the depth buffer, in pixels at render resolution, pointing from the current
+    /// frame to the previous frame. It can be used as [`crate::DlssRenderParameters::motion_vectors`] with
+    /// [`crate::DlssRenderParameters::motion_vector_scale`] set to `None`.
+    pub fn encode(
+        &mut self,
+        inputs: &DlssMotionVectorInputs,
+        command_encoder: &mut CommandEncoder,
+        device: &Device,
+    ) -> Result<DlssTexture<'_>, DlssError> {
+        let depth_format = inputs.depth.format();
+        if !depth_format.has_depth_aspect() {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "DlssMotionVectorPass depth must be a depth texture view, but has format {depth_format:?}"
+            )));
+        }
+
+        let size = inputs.depth.size();
+        if let Some(object_velocity) = &inputs.object_velocity {
+            if object_velocity.size() != size {
+                return Err(DlssError::InvalidRenderParameters(format!(
+                    "DlssMotionVectorPass object_velocity has size {}, but must be the same size as depth ({size})",
+                    object_velocity.size()
+                )));
+            }
+            let format = object_velocity.format();
+            if format.components() < 3 {
+                return Err(DlssError::InvalidRenderParameters(format!(
+                    "DlssMotionVectorPass object_velocity format {format:?} has no third channel for the coverage mask"
+                )));
+            }
+        }
+
+        self.output.resize(size, device);
+        let output = self.output.texture();
+
+        let previous_clip_from_clip =
+            inputs.previous_view_projection * inputs.view_projection.inverse();
+        let jitter_ndc = inputs.jitter_offset * Vec2::new(2.0, -2.0) / size.as_vec2();
+        let uniforms = [
+            previous_clip_from_clip.uniform_words(),
+            jitter_ndc.uniform_words(),
+            inputs.object_velocity_scale.uniform_words(),
+        ]
+        .concat();
+        let uniform_buffer = create_uniform_buffer(device, "dlss_motion_vectors", &uniforms);
+
+        let mut entries = vec![
+            BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(inputs.depth.view),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: BindingResource::TextureView(output.view),
+            },
+        ];
+        let (label, pipeline, layout) = match &inputs.object_velocity {
+            Some(object_velocity) => {
+                entries.push(BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(object_velocity.view),
+                });
+                (
+                    "dlss_merge_motion_vectors",
+                    &self.merge_pipeline,
+                    &self.merge_bind_group_layout,
+                )
+            }
+            None => (
+                "dlss_camera_motion_vectors",
+                &self.camera_pipeline,
+                &self.camera_bind_group_layout,
+            ),
+        };
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(label),
+            layout,
+            entries: &entries,
+        });
+
+        dispatch(command_encoder, label, pipeline, &bind_group, size);
+
+        Ok(output)
+    }
+}
+
+/// Describes how motion vectors are stored, so that the scale DLSS needs to convert them can be computed automatically.
+///
+/// DLSS expects motion vectors in pixels, pointing from the current frame to the previous frame, with Y pointing down.
+/// This is the [`Default`] convention, and the one produced by [`DlssMotionVectorPass`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct MotionVectorConvention {
+    pub units: MotionVectorUnits,
+    pub y_axis: MotionVectorYAxis,
+    pub direction: MotionVectorDirection,
+}
+
+/// Units motion vectors are stored in, relative to the rendered region of the motion vector texture.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum MotionVectorUnits {
+    /// Pixels, where 1.0 is one texel.
+    #[default]
+    Pixels,
+    /// Texture coordinates, where 1.0 is the full width or height.
+    Uv,
+    /// Normalized device coordinates, where 2.0 is the full width or height.
+    Ndc,
+}
+
+/// Direction of the Y axis motion vectors are stored in.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum MotionVectorYAxis {
+    #[default]
+    Down,
+    Up,
+}
+
+/// Which frame motion vectors point towards.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum MotionVectorDirection {
+    /// Motion vectors point from a pixel's position in the current frame to its position in the previous frame.
+    #[default]
+    CurrentToPrevious,
+    /// Motion vectors point from a pixel's position in the previous frame to its position in the current frame.
+    PreviousToCurrent,
+}
+
+impl MotionVectorConvention {
+    /// The value for DLSS's motion vector scale, given the size of the rendered region of the motion vector texture.
+    pub fn scale(&self, motion_vector_resolution: UVec2) -> Vec2 {
+        let resolution = motion_vector_resolution.as_vec2();
+        let mut scale = match self.units {
+            MotionVectorUnits::Pixels => Vec2::ONE,
+            MotionVectorUnits::Uv => resolution,
+            MotionVectorUnits::Ndc => resolution * 0.5,
+        };
+        if self.y_axis == MotionVectorYAxis::Up {
+            scale.y = -scale.y;
+        }
+        if self.direction == MotionVectorDirection::PreviousToCurrent {
+            scale = -scale;
+        }
+        scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: UVec2 = UVec2::new(200, 100);
+
+    /// A pixel that moved from (100, 50) in the previous frame to (110, 40) in the current frame.
+    const PREVIOUS_PIXEL: Vec2 = Vec2::new(100.0, 50.0);
+    const CURRENT_PIXEL: Vec2 = Vec2::new(110.0, 40.0);
+
+    /// Encode the test pixel's motion using the given convention.
+    fn encode(convention: MotionVectorConvention, resolution: UVec2) -> Vec2 {
+        let to_position = |pixel: Vec2| {
+            let uv = pixel / resolution.as_vec2();
+            let mut position = match convention.units {
+                MotionVectorUnits::Pixels => pixel,
+                MotionVectorUnits::Uv => uv,
+                MotionVectorUnits::Ndc => uv * 2.0 - 1.0,
+            };
+            if convention.y_axis == MotionVectorYAxis::Up {
+                position.y = -position.y;
+            }
+            position
+        };
+
+        let (previous, current) = (to_position(PREVIOUS_PIXEL), to_position(CURRENT_PIXEL));
+        match convention.direction {
+            MotionVectorDirection::CurrentToPrevious => previous - current,
+            MotionVectorDirection::PreviousToCurrent => current - previous,
+        }
+    }
+
+    fn assert_converts_to_dlss(convention: MotionVectorConvention, resolution: UVec2) {
+        let scaled = encode(convention, resolution) * convention.scale(resolution);
+        let expected = PREVIOUS_PIXEL - CURRENT_PIXEL;
+        assert!(
+            scaled.abs_diff_eq(expected, 1e-3),
+            "{convention:?} at {resolution} produced {scaled} instead of {expected}"
+        );
+    }
+
+    fn all_conventions() -> impl Iterator<Item = MotionVectorConvention> {
+        let units = [
+            MotionVectorUnits::Pixels,
+            MotionVectorUnits::Uv,
+            MotionVectorUnits::Ndc,
+        ];
+        let y_axes = [MotionVectorYAxis::Down, MotionVectorYAxis::Up];
+        let directions = [
+            MotionVectorDirection::CurrentToPrevious,
+            MotionVectorDirection::PreviousToCurrent,
+        ];
+
+        units.into_iter().flat_map(move |units| {
+            y_axes.into_iter().flat_map(move |y_axis| {
+                directions
+                    .into_iter()
+                    .map(move |direction| MotionVectorConvention {
+                        units,
+                        y_axis,
+                        direction,
+                    })
+            })
+        })
+    }
+
+    #[test]
+    fn default_convention_is_unscaled() {
+        assert_eq!(
+            MotionVectorConvention::default().scale(RESOLUTION),
+            Vec2::ONE
+        );
+    }
+
+    #[test]
+    fn every_convention_converts_to_dlss() {
+        for convention in all_conventions() {
+            assert_converts_to_dlss(convention, RESOLUTION);
+        }
+    }
+
+    #[test]
+    fn scale_follows_dynamic_resolution() {
+        for convention in all_conventions() {
+            for resolution in [UVec2::new(150, 75), UVec2::new(133, 61), RESOLUTION * 2] {
+                assert_converts_to_dlss(convention, resolution);
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+struct Uniforms {
+    // Maps the current frame's unjittered clip space to the previous frame's unjittered clip space
+    previous_clip_from_clip: mat4x4<f32>,
+    // Current frame's jitter offset, in NDC
+    jitter_ndc: vec2<f32>,
+    // Converts object_velocity texels to pixels
+    object_velocity_scale: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var depth: texture_depth_2d;
+@group(0) @binding(2) var output: texture_storage_2d<rg32float, write>;
+@group(0) @binding(3) var object_velocity: texture_2d<f32>;
+
+// Motion in pixels from the current pixel to its position in the previous frame
+fn camera_motion(pixel: vec2<u32>, size: vec2<u32>) -> vec2<f32> {
+    let uv = (vec2<f32>(pixel) + 0.5) / vec2<f32>(size);
+    let ndc = vec2(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0) - uniforms.jitter_ndc;
+    let depth = textureLoad(depth, pixel, 0);
+
+    // Reprojecting clip space directly keeps points at infinity (e.g. reverse-Z skyboxes) finite
+    let previous_clip = uniforms.previous_clip_from_clip * vec4(ndc, depth, 1.0);
+    let previous_ndc = previous_clip.xy / previous_clip.w;
+
+    return (previous_ndc - ndc) * vec2(0.5, -0.5) * vec2<f32>(size);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn camera_motion_vectors(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if any(global_id.xy >= size) {
+        return;
+    }
+
+    let motion = camera_motion(global_id.xy, size);
+    textureStore(output, global_id.xy, vec4(motion, 0.0, 0.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn merge_motion_vectors(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if any(global_id.xy >= size) {
+        return;
+    }
+
+    // Pixels not covered by the per-object velocity pass, according to its third channel, fall back to camera motion
+    let velocity = textureLoad(object_velocity, global_id.xy, 0);
+    var motion = velocity.xy * uniforms.object_velocity_scale;
+    if velocity.z < 0.5 {
+        motion = camera_motion(global_id.xy, size);
+    }
+    textureStore(output, global_id.xy, vec4(motion, 0.0, 0.0));
+}
@@ -0,0 +1,218 @@
+use crate::{
+    DlssError, DlssTexture,
+    compute_pass::{
+        ScratchTexture, UniformValue, create_compute_pipeline, create_shader_module,
+        create_uniform_buffer, dispatch, multisampled_texture_entry, storage_texture_entry,
+        uniform_entry,
+    },
+};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindingResource, CommandEncoder, ComputePipeline, Device, TextureFormat, TextureSampleType,
+    TextureUsages,
+};
+
+/// How [`DlssMsaaResolvePass`] combines the samples of a depth buffer.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum DlssDepthResolve {
+    /// The sample closest to the camera, which is also the sample motion vectors are taken from.
+    ///
+    /// Equivalent to [`Self::Min`], or [`Self::Max`] with [`DlssMsaaResolveInputs::inverted_depth`], so that the depth
+    /// convention does not need to be repeated here.
+    #[default]
+    Closest,
+    /// The smallest depth value.
+    Min,
+    /// The largest depth value.
+    Max,
+}
+
+/// Multisampled textures for [`DlssMsaaResolvePass::encode`].
+///
+/// All textures must have the same size, and the sample count the pass was created with.
+#[derive(Clone, Copy)]
+pub struct DlssMsaaResolveInputs<'a> {
+    /// HDR or LDR color.
+    pub color: DlssTexture<'a>,
+    /// Depth buffer.
+    ///
+    /// Must be a 2D view with [`wgpu::TextureAspect::DepthOnly`] if the texture has a combined depth-stencil format.
+    pub depth: DlssTexture<'a>,
+    /// Motion vectors, in any convention, see [`crate::DlssContext::set_motion_vector_convention`].
+    pub motion_vectors: DlssTexture<'a>,
+    pub depth_resolve: DlssDepthResolve,
+    /// Whether depth is reversed (1.0 is closest), see [`crate::DlssFeatureFlags::InvertedDepth`].
+    pub inverted_depth: bool,
+}
+
+/// Single-sample textures produced by [`DlssMsaaResolvePass`], for use in [`crate::DlssRenderParameters`].
+pub struct DlssMsaaResolveOutputs<'a> {
+    /// `Rgba16Float` color.
+    pub color: DlssTexture<'a>,
+    /// `R32Float` depth.
+    pub depth: DlssTexture<'a>,
+    /// `Rg32Float` motion vectors, in the same convention as the input.
+    pub motion_vectors: DlssTexture<'a>,
+}
+
+/// Compute pass that resolves multisampled color, depth and motion vectors, as DLSS requires single-sample inputs.
+///
+/// Color is averaged in a reversibly tonemapped space, so that bright samples do not alias edges.
+/// Motion vectors are taken from the sample closest to the camera.
+pub struct DlssMsaaResolvePass {
+    sample_count: u32,
+    pipeline: ComputePipeline,
+    bind_group_layout: BindGroupLayout,
+    color: ScratchTexture,
+    depth: ScratchTexture,
+    motion_vectors: ScratchTexture,
+}
+
+impl DlssMsaaResolvePass {
+    /// Create a pass for textures with `sample_count` samples, which must be greater than 1.
+    pub fn new(sample_count: u32, device: &Device) -> Result<Self, DlssError> {
+        if sample_count <= 1 {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "DlssMsaaResolvePass sample count must be greater than 1, but is {sample_count}"
+            )));
+        }
+
+        let shader_module = create_shader_module(
+            device,
+            "dlss_msaa_resolve",
+            include_str!("msaa_resolve.wgsl"),
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("dlss_msaa_resolve"),
+            entries: &[
+                uniform_entry(0),
+                multisampled_texture_entry(1, TextureSampleType::Float { filterable: false }),
+                multisampled_texture_entry(2, TextureSampleType::Depth),
+                multisampled_texture_entry(3, TextureSampleType::Float { filterable: false }),
+                storage_texture_entry(4, TextureFormat::Rgba16Float),
+                storage_texture_entry(5, TextureFormat::R32Float),
+                storage_texture_entry(6, TextureFormat::Rg32Float),
+            ],
+        });
+
+        let output = |label, format| {
+            ScratchTexture::new(
+                label,
+                format,
+                TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            )
+        };
+
+        Ok(Self {
+            sample_count,
+            pipeline: create_compute_pipeline(
+                device,
+                "dlss_msaa_resolve",
+                &shader_module,
+                "resolve",
+                &bind_group_layout,
+            ),
+            bind_group_layout,
+            color: output("dlss_msaa_resolve_color", TextureFormat::Rgba16Float),
+            depth: output("dlss_msaa_resolve_depth", TextureFormat::R32Float),
+            motion_vectors: output("dlss_msaa_resolve_motion_vectors", TextureFormat::Rg32Float),
+        })
+    }
+
+    /// Encode commands to resolve `inputs` into single-sample textures owned by this pass.
+    pub fn encode(
+        &mut self,
+        inputs: &DlssMsaaResolveInputs,
+        command_encoder: &mut CommandEncoder,
+        device: &Device,
+    ) -> Result<DlssMsaaResolveOutputs<'_>, DlssError> {
+        let depth_format = inputs.depth.format();
+        if !depth_format.has_depth_aspect() {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "DlssMsaaResolvePass depth must be a depth texture view, but has format {depth_format:?}"
+            )));
+        }
+
+        let size = inputs.depth.size();
+        for (name, texture) in [
+            ("color", &inputs.color),
+            ("depth", &inputs.depth),
+            ("motion_vectors", &inputs.motion_vectors),
+        ] {
+            let sample_count = texture.texture.sample_count();
+            if sample_count != self.sample_count {
+                return Err(DlssError::InvalidRenderParameters(format!(
+                    "DlssMsaaResolvePass {name} has sample count {sample_count}, but the pass was created with sample count {}",
+                    self.sample_count
+                )));
+            }
+            if texture.size() != size {
+                return Err(DlssError::InvalidRenderParameters(format!(
+                    "DlssMsaaResolvePass {name} has size {}, but must be the same size as depth ({size})",
+                    texture.size()
+                )));
+            }
+        }
+
+        for texture in [&mut self.color, &mut self.depth, &mut self.motion_vectors] {
+            texture.resize(size, device);
+        }
+
+        let depth_resolve = match inputs.depth_resolve {
+            DlssDepthResolve::Closest => 0u32,
+            DlssDepthResolve::Min => 1,
+            DlssDepthResolve::Max => 2,
+        };
+        let uniforms = [
+            depth_resolve.uniform_words(),
+            (inputs.inverted_depth as u32).uniform_words(),
+        ]
+        .concat();
+        let uniform_buffer = create_uniform_buffer(device, "dlss_msaa_resolve", &uniforms);
+
+        let outputs = DlssMsaaResolveOutputs {
+            color: self.color.texture(),
+            depth: self.depth.texture(),
+            motion_vectors: self.motion_vectors.texture(),
+        };
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("dlss_msaa_resolve"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                view_entry(1, &inputs.color),
+                view_entry(2, &inputs.depth),
+                view_entry(3, &inputs.motion_vectors),
+                view_entry(4, &outputs.color),
+                view_entry(5, &outputs.depth),
+                view_entry(6, &outputs.motion_vectors),
+            ],
+        });
+        dispatch(
+            command_encoder,
+            "dlss_msaa_resolve",
+            &self.pipeline,
+            &bind_group,
+            size,
+        );
+
+        Ok(outputs)
+    }
+
+    /// The sample count this pass was created with.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+}
+
+fn view_entry<'a>(binding: u32, texture: &DlssTexture<'a>) -> BindGroupEntry<'a> {
+    BindGroupEntry {
+        binding,
+        resource: BindingResource::TextureView(texture.view),
+    }
+}
@@ -0,0 +1,73 @@
+const DEPTH_RESOLVE_CLOSEST: u32 = 0u;
+const DEPTH_RESOLVE_MIN: u32 = 1u;
+
+struct Uniforms {
+    depth_resolve: u32,
+    inverted_depth: u32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var color: texture_multisampled_2d<f32>;
+@group(0) @binding(2) var depth: texture_depth_multisampled_2d;
+@group(0) @binding(3) var motion_vectors: texture_multisampled_2d<f32>;
+@group(0) @binding(4) var color_output: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(5) var depth_output: texture_storage_2d<r32float, write>;
+@group(0) @binding(6) var motion_vector_output: texture_storage_2d<rg32float, write>;
+
+fn max3(c: vec3<f32>) -> f32 {
+    return max(c.r, max(c.g, c.b));
+}
+
+// Reversible tonemapper, which keeps bright samples from dominating edges
+fn tonemap(c: vec3<f32>) -> vec3<f32> {
+    return c / (1.0 + max3(c));
+}
+
+fn inverse_tonemap(c: vec3<f32>) -> vec3<f32> {
+    return c / max(1.0 - max3(c), 1e-4);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn resolve(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if any(global_id.xy >= textureDimensions(depth_output)) {
+        return;
+    }
+    let pixel = vec2<i32>(global_id.xy);
+    let sample_count = i32(textureNumSamples(color));
+
+    var color_sum = vec3(0.0);
+    var alpha_sum = 0.0;
+    var closest_sample = 0;
+    var closest_depth = textureLoad(depth, pixel, 0);
+    var min_depth = closest_depth;
+    var max_depth = closest_depth;
+    for (var i = 0; i < sample_count; i++) {
+        let sample = textureLoad(color, pixel, i);
+        color_sum += tonemap(sample.rgb);
+        alpha_sum += sample.a;
+
+        let depth = textureLoad(depth, pixel, i);
+        min_depth = min(min_depth, depth);
+        max_depth = max(max_depth, depth);
+        let closer = select(depth < closest_depth, depth > closest_depth, uniforms.inverted_depth != 0u);
+        if closer {
+            closest_depth = depth;
+            closest_sample = i;
+        }
+    }
+
+    var resolved_depth = max_depth;
+    if uniforms.depth_resolve == DEPTH_RESOLVE_CLOSEST {
+        resolved_depth = closest_depth;
+    } else if uniforms.depth_resolve == DEPTH_RESOLVE_MIN {
+        resolved_depth = min_depth;
+    }
+
+    // Motion of the closest surface, so that edges move with the foreground object
+    let motion_vector = textureLoad(motion_vectors, pixel, closest_sample).xy;
+
+    let color = inverse_tonemap(color_sum / f32(sample_count));
+    textureStore(color_output, pixel, vec4(color, alpha_sum / f32(sample_count)));
+    textureStore(depth_output, pixel, vec4(resolved_depth, 0.0, 0.0, 0.0));
+    textureStore(motion_vector_output, pixel, vec4(motion_vector, 0.0, 0.0));
+}
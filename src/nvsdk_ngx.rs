@@ -19,6 +19,7 @@ type VkInstance = ash::vk::Instance;
 type VkPhysicalDevice = ash::vk::PhysicalDevice;
 
 use glam::UVec2;
+use std::fmt;
 
 /// How much DLSS should upscale by.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
@@ -63,16 +64,82 @@ impl DlssPerfQualityMode {
             }
         }
     }
+
+    /// Ratio of render resolution to upscaled resolution, matching DLSS's defaults.
+    ///
+    /// Used by upscalers that cannot query NGX for their render resolution.
+    pub(crate) fn render_scale(&self, upscaled_resolution: UVec2) -> f32 {
+        match self.as_perf_quality_value(upscaled_resolution) {
+            NVSDK_NGX_PerfQuality_Value_NVSDK_NGX_PerfQuality_Value_MaxQuality => 2.0 / 3.0,
+            NVSDK_NGX_PerfQuality_Value_NVSDK_NGX_PerfQuality_Value_Balanced => 0.58,
+            NVSDK_NGX_PerfQuality_Value_NVSDK_NGX_PerfQuality_Value_MaxPerf => 0.5,
+            NVSDK_NGX_PerfQuality_Value_NVSDK_NGX_PerfQuality_Value_UltraPerformance => 1.0 / 3.0,
+            _ => 1.0,
+        }
+    }
+
+    /// [`Self::render_scale`] applied to `upscaled_resolution`.
+    pub(crate) fn render_resolution(&self, upscaled_resolution: UVec2) -> UVec2 {
+        (upscaled_resolution.as_vec2() * self.render_scale(upscaled_resolution))
+            .round()
+            .as_uvec2()
+            .max(UVec2::ONE)
+    }
+}
+
+/// Tonemapper applied by the application after DLSS, used as a hint to improve quality.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum DlssToneMapperType {
+    /// No tonemapper specified.
+    #[default]
+    Unspecified,
+    Reinhard,
+    /// Color divided by one plus luminance.
+    OneOverLuma,
+    Aces,
 }
 
+impl DlssToneMapperType {
+    pub(crate) fn as_tone_mapper_type(&self) -> NVSDK_NGX_ToneMapperType {
+        match self {
+            Self::Unspecified => NVSDK_NGX_ToneMapperType_NVSDK_NGX_TONEMAPPER_STRING,
+            Self::Reinhard => NVSDK_NGX_ToneMapperType_NVSDK_NGX_TONEMAPPER_REINHARD,
+            Self::OneOverLuma => NVSDK_NGX_ToneMapperType_NVSDK_NGX_TONEMAPPER_ONEOVERLUMA,
+            Self::Aces => NVSDK_NGX_ToneMapperType_NVSDK_NGX_TONEMAPPER_ACES,
+        }
+    }
+}
+
+/// Slots for GBuffer attributes in [`crate::DlssGuideTextures::gbuffer`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DlssGBufferAttribute {
+    Albedo = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_ALBEDO as isize,
+    Roughness = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_ROUGHNESS as isize,
+    Metallic = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_METALLIC as isize,
+    Specular = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_SPECULAR as isize,
+    Subsurface = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_SUBSURFACE as isize,
+    Normals = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_NORMALS as isize,
+    ShadingModelId = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_SHADINGMODELID as isize,
+    MaterialId = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_MATERIALID as isize,
+    SpecularAlbedo = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_SPECULAR_ALBEDO as isize,
+    IndirectAlbedo = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_INDIRECT_ALBEDO as isize,
+    SpecularMotionVectors = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_SPECULAR_MVEC as isize,
+    DisocclusionMask = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_DISOCCL_MASK as isize,
+    Emissive = NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFER_EMISSIVE as isize,
+}
+
+/// Number of GBuffer attribute slots DLSS accepts.
+pub(crate) const GBUFFER_ATTRIBUTE_COUNT: usize =
+    NVSDK_NGX_GBufferType_NVSDK_NGX_GBUFFERTYPE_NUM as usize;
+
 bitflags::bitflags! {
     /// Flags for creating a [`crate::DlssContext`].
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct DlssFeatureFlags: NVSDK_NGX_DLSS_Feature_Flags {
         /// Use an HDR texture for [`crate::DlssRenderParameters::color`] instead of an SDR texture.
         const HighDynamicRange = NVSDK_NGX_DLSS_Feature_Flags_NVSDK_NGX_DLSS_Feature_Flags_IsHDR;
-        /// Motion vector values in [`crate::DlssRenderParameters::motion_vectors`] are at the upscaled resolution,
-        /// instead of render resolution.
+        /// Motion vector values in [`crate::DlssRenderParameters::motion_vectors`] are at render resolution,
+        /// instead of the upscaled resolution.
         const LowResolutionMotionVectors = NVSDK_NGX_DLSS_Feature_Flags_NVSDK_NGX_DLSS_Feature_Flags_MVLowRes;
         /// Motion vector values in [`crate::DlssRenderParameters::motion_vectors`] contain jitter.
         const JitteredMotionVectors = NVSDK_NGX_DLSS_Feature_Flags_NVSDK_NGX_DLSS_Feature_Flags_MVJittered;
@@ -98,6 +165,97 @@ impl DlssFeatureFlags {
 /// Errors thrown by DLSS.
 #[derive(thiserror::Error, Debug)]
 pub enum DlssError {
+    /// A call into the NGX SDK failed.
+    #[error("{entry_point} failed{context}")]
+    Ngx {
+        /// Name of the NGX function that failed.
+        entry_point: &'static str,
+        /// Settings that were in use when the call failed.
+        context: DlssErrorContext,
+        /// The error reported by NGX.
+        #[source]
+        source: NgxError,
+    },
+    /// DLSS was reported as unavailable before any NGX call failed. See also [`Self::is_feature_not_supported`].
+    #[error("DLSS is not supported by the current system, hardware, and/or graphics API.")]
+    FeatureNotSupported,
+    /// The provided [`crate::DlssRenderParameters`] are not usable by DLSS.
+    #[error("Invalid DlssRenderParameters: {0}")]
+    InvalidRenderParameters(String),
+}
+
+impl DlssError {
+    /// The underlying error reported by NGX, if any.
+    pub fn ngx_error(&self) -> Option<NgxError> {
+        match self {
+            Self::Ngx { source, .. } => Some(*source),
+            Self::FeatureNotSupported | Self::InvalidRenderParameters(_) => None,
+        }
+    }
+
+    /// Whether DLSS is not supported, either as reported up front with [`Self::FeatureNotSupported`], or by an NGX call
+    /// failing with [`NgxError::FeatureNotSupported`].
+    pub fn is_feature_not_supported(&self) -> bool {
+        matches!(
+            self,
+            Self::FeatureNotSupported
+                | Self::Ngx {
+                    source: NgxError::FeatureNotSupported,
+                    ..
+                }
+        )
+    }
+
+    pub(crate) fn with_context(mut self, new_context: DlssErrorContext) -> Self {
+        if let Self::Ngx { context, .. } = &mut self {
+            *context = new_context;
+        }
+        self
+    }
+}
+
+/// Settings attached to a [`DlssError`] to help identify what went wrong.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct DlssErrorContext {
+    pub upscaled_resolution: Option<UVec2>,
+    pub render_resolution: Option<UVec2>,
+    pub perf_quality_mode: Option<DlssPerfQualityMode>,
+    pub feature_flags: Option<DlssFeatureFlags>,
+}
+
+impl fmt::Display for DlssErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut settings = Vec::new();
+        if let Some(resolution) = self.upscaled_resolution {
+            settings.push(format!(
+                "upscaled resolution {}x{}",
+                resolution.x, resolution.y
+            ));
+        }
+        if let Some(resolution) = self.render_resolution {
+            settings.push(format!(
+                "render resolution {}x{}",
+                resolution.x, resolution.y
+            ));
+        }
+        if let Some(perf_quality_mode) = self.perf_quality_mode {
+            settings.push(format!("perf quality mode {perf_quality_mode:?}"));
+        }
+        if let Some(feature_flags) = self.feature_flags {
+            settings.push(format!("feature flags {feature_flags:?}"));
+        }
+
+        if settings.is_empty() {
+            Ok(())
+        } else {
+            write!(f, " ({})", settings.join(", "))
+        }
+    }
+}
+
+/// Error codes returned by the NGX SDK.
+#[derive(thiserror::Error, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum NgxError {
     #[error(
         "The NGX SDK or a specific feature is not supported by the current system, hardware, and/or graphics API."
     )]
@@ -162,31 +320,40 @@ pub enum DlssError {
     NotImplemented,
 }
 
-pub fn check_ngx_result(result: NVSDK_NGX_Result) -> Result<(), DlssError> {
+pub fn check_ngx_result(
+    entry_point: &'static str,
+    result: NVSDK_NGX_Result,
+) -> Result<(), DlssError> {
+    ngx_error(result).map_err(|source| DlssError::Ngx {
+        entry_point,
+        context: DlssErrorContext::default(),
+        source,
+    })
+}
+
+fn ngx_error(result: NVSDK_NGX_Result) -> Result<(), NgxError> {
     match result {
         NVSDK_NGX_Result_Success => Ok(()),
-        NVSDK_NGX_Result_FAIL_FeatureNotSupported => Err(DlssError::FeatureNotSupported),
-        NVSDK_NGX_RESULT_FAIL_PlatformError => Err(DlssError::PlatformError),
-        NVSDK_NGX_RESULT_FAIL_FeatureAlreadyExists => Err(DlssError::FeatureAlreadyExists),
-        NVSDK_NGX_RESULT_FAIL_FeatureNotFound => Err(DlssError::FeatureNotFound),
-        NVSDK_NGX_RESULT_FAIL_InvalidParameters => Err(DlssError::InvalidParameters),
-        NVSDK_NGX_RESULT_FAIL_ScratchBufferTooSmall => Err(DlssError::ScratchBufferTooSmall),
-        NVSDK_NGX_RESULT_FAIL_NotInitialized => Err(DlssError::NotInitialized),
-        NVSDK_NGX_RESULT_FAIL_UnsupportedInputFormat => Err(DlssError::UnsupportedInputFormat),
-        NVSDK_NGX_RESULT_FAIL_RWFlagMissing => Err(DlssError::RWFlagMissing),
-        NVSDK_NGX_RESULT_FAIL_MissingInput => Err(DlssError::MissingInput),
-        NVSDK_NGX_RESULT_FAIL_UnableToInitializeFeature => {
-            Err(DlssError::UnableToInitializeFeature)
-        }
-        NVSDK_NGX_RESULT_FAIL_OutOfDate => Err(DlssError::OutOfDate),
-        NVSDK_NGX_RESULT_FAIL_OutOfGPUMemory => Err(DlssError::OutOfGPUMemory),
-        NVSDK_NGX_RESULT_FAIL_UnsupportedFormat => Err(DlssError::UnsupportedFormat),
+        NVSDK_NGX_Result_FAIL_FeatureNotSupported => Err(NgxError::FeatureNotSupported),
+        NVSDK_NGX_RESULT_FAIL_PlatformError => Err(NgxError::PlatformError),
+        NVSDK_NGX_RESULT_FAIL_FeatureAlreadyExists => Err(NgxError::FeatureAlreadyExists),
+        NVSDK_NGX_RESULT_FAIL_FeatureNotFound => Err(NgxError::FeatureNotFound),
+        NVSDK_NGX_RESULT_FAIL_InvalidParameters => Err(NgxError::InvalidParameters),
+        NVSDK_NGX_RESULT_FAIL_ScratchBufferTooSmall => Err(NgxError::ScratchBufferTooSmall),
+        NVSDK_NGX_RESULT_FAIL_NotInitialized => Err(NgxError::NotInitialized),
+        NVSDK_NGX_RESULT_FAIL_UnsupportedInputFormat => Err(NgxError::UnsupportedInputFormat),
+        NVSDK_NGX_RESULT_FAIL_RWFlagMissing => Err(NgxError::RWFlagMissing),
+        NVSDK_NGX_RESULT_FAIL_MissingInput => Err(NgxError::MissingInput),
+        NVSDK_NGX_RESULT_FAIL_UnableToInitializeFeature => Err(NgxError::UnableToInitializeFeature),
+        NVSDK_NGX_RESULT_FAIL_OutOfDate => Err(NgxError::OutOfDate),
+        NVSDK_NGX_RESULT_FAIL_OutOfGPUMemory => Err(NgxError::OutOfGPUMemory),
+        NVSDK_NGX_RESULT_FAIL_UnsupportedFormat => Err(NgxError::UnsupportedFormat),
         NVSDK_NGX_RESULT_FAIL_UnableToWriteToAppDataPath => {
-            Err(DlssError::UnableToWriteToAppDataPath)
+            Err(NgxError::UnableToWriteToAppDataPath)
         }
-        NVSDK_NGX_RESULT_FAIL_UnsupportedParameter => Err(DlssError::UnsupportedParameter),
-        NVSDK_NGX_RESULT_FAIL_Denied => Err(DlssError::Denied),
-        NVSDK_NGX_RESULT_FAIL_NotImplemented => Err(DlssError::NotImplemented),
+        NVSDK_NGX_RESULT_FAIL_UnsupportedParameter => Err(NgxError::UnsupportedParameter),
+        NVSDK_NGX_RESULT_FAIL_Denied => Err(NgxError::Denied),
+        NVSDK_NGX_RESULT_FAIL_NotImplemented => Err(NgxError::NotImplemented),
         _ => unreachable!(),
     }
 }
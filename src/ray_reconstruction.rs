@@ -0,0 +1,437 @@
+use crate::{
+    DlssDebugOptions, DlssError, DlssRenderParameters, DlssSdk, DlssTexture,
+    MotionVectorConvention,
+    context::{
+        ExposureResource, ExposureTexture, FeatureConfig, GuideResources, frame_time_delta,
+        release_feature, resource_ptr,
+    },
+    feature_info::DlssFeature,
+    nvsdk_ngx::*,
+};
+use glam::{Mat4, UVec2, Vec2};
+use std::{
+    iter, mem,
+    ops::RangeInclusive,
+    ptr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use wgpu::{
+    Adapter, CommandEncoder, CommandEncoderDescriptor, Device, Queue, Texture, TextureTransition,
+    TextureUses, hal::api::Vulkan,
+};
+
+/// Where [`DlssRayReconstructionRenderParameters`] stores roughness.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum DlssRoughnessMode {
+    /// Roughness is read from [`DlssRayReconstructionRenderParameters::roughness`].
+    #[default]
+    Unpacked,
+    /// Roughness is read from the alpha channel of [`DlssRayReconstructionRenderParameters::normals`].
+    Packed,
+}
+
+/// What [`DlssRenderParameters::depth`] contains when used for ray reconstruction.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum DlssDepthType {
+    /// Positive view space distance along the camera's forward axis.
+    Linear,
+    /// The depth buffer written by rasterization.
+    #[default]
+    Hardware,
+}
+
+/// Layout of the guide buffers given to a [`DlssRayReconstructionContext`], fixed at creation.
+///
+/// [`crate::DlssGuidePackingPass`] can convert a typical G-buffer into either layout.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct DlssRayReconstructionGuideLayout {
+    pub roughness_mode: DlssRoughnessMode,
+    pub depth_type: DlssDepthType,
+}
+
+impl DlssRoughnessMode {
+    fn as_roughness_mode(self) -> NVSDK_NGX_DLSS_Roughness_Mode {
+        match self {
+            Self::Unpacked => NVSDK_NGX_DLSS_Roughness_Mode_NVSDK_NGX_DLSS_Roughness_Mode_Unpacked,
+            Self::Packed => NVSDK_NGX_DLSS_Roughness_Mode_NVSDK_NGX_DLSS_Roughness_Mode_Packed,
+        }
+    }
+}
+
+impl DlssDepthType {
+    fn as_depth_type(self) -> NVSDK_NGX_DLSS_Depth_Type {
+        match self {
+            Self::Linear => NVSDK_NGX_DLSS_Depth_Type_NVSDK_NGX_DLSS_Depth_Type_Linear,
+            Self::Hardware => NVSDK_NGX_DLSS_Depth_Type_NVSDK_NGX_DLSS_Depth_Type_HW,
+        }
+    }
+}
+
+/// Inputs and output resources needed for rendering DLSS Ray Reconstruction.
+pub struct DlssRayReconstructionRenderParameters<'a> {
+    /// Inputs shared with DLSS Super Resolution. [`DlssRenderParameters::color`] is the noisy ray traced color.
+    pub base: DlssRenderParameters<'a>,
+    /// Diffuse albedo of the primary surface.
+    pub diffuse_albedo: DlssTexture<'a>,
+    /// Specular albedo of the primary surface, i.e. the preintegrated specular reflectance.
+    pub specular_albedo: DlssTexture<'a>,
+    /// World space normals of the primary surface.
+    pub normals: DlssTexture<'a>,
+    /// Linear roughness of the primary surface, read from the first channel.
+    ///
+    /// Must be `None` if the context uses [`DlssRoughnessMode::Packed`], and `Some` otherwise.
+    pub roughness: Option<DlssTexture<'a>>,
+    /// Optional distance from the primary surface to the first hit of its specular ray.
+    pub specular_hit_distance: Option<DlssTexture<'a>>,
+    /// The camera's world-to-view matrix.
+    pub world_to_view: Mat4,
+    /// The camera's view-to-clip matrix, without jitter.
+    pub view_to_clip: Mat4,
+}
+
+impl<'a> DlssRayReconstructionRenderParameters<'a> {
+    fn validate(&self, guide_layout: DlssRayReconstructionGuideLayout) -> Result<(), DlssError> {
+        self.base.validate()?;
+
+        match (guide_layout.roughness_mode, &self.roughness) {
+            (DlssRoughnessMode::Unpacked, None) => {
+                return Err(DlssError::InvalidRenderParameters(
+                    "roughness texture is required with DlssRoughnessMode::Unpacked".to_owned(),
+                ));
+            }
+            (DlssRoughnessMode::Packed, Some(_)) => {
+                return Err(DlssError::InvalidRenderParameters(
+                    "roughness texture must be None with DlssRoughnessMode::Packed, as roughness is read from the normals".to_owned(),
+                ));
+            }
+            _ => {}
+        }
+
+        let render_size = self.base.color.size();
+        for (name, texture) in self.named_textures() {
+            if texture.size() != render_size {
+                return Err(DlssError::InvalidRenderParameters(format!(
+                    "{name} texture has size {}, but must have size {render_size}",
+                    texture.size()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Textures specific to ray reconstruction, excluding those in [`Self::base`].
+    fn named_textures(&self) -> impl Iterator<Item = (&'static str, &DlssTexture<'a>)> {
+        [
+            ("diffuse_albedo", Some(&self.diffuse_albedo)),
+            ("specular_albedo", Some(&self.specular_albedo)),
+            ("normals", Some(&self.normals)),
+            ("roughness", self.roughness.as_ref()),
+            ("specular_hit_distance", self.specular_hit_distance.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, texture)| Some((name, texture?)))
+    }
+
+    fn barrier_list(&self) -> impl Iterator<Item = TextureTransition<&'a Texture>> {
+        self.base
+            .barrier_list()
+            .chain(self.named_textures().map(|(_, texture)| TextureTransition {
+                texture: texture.texture,
+                selector: Some(texture.selector()),
+                state: TextureUses::RESOURCE,
+            }))
+    }
+}
+
+/// Camera-specific object for using DLSS Ray Reconstruction, which denoises and upscales ray traced color.
+///
+/// Requires the ray reconstruction library to be available alongside the DLSS library, see
+/// [`DlssSdk::is_ray_reconstruction_supported`].
+pub struct DlssRayReconstructionContext {
+    config: FeatureConfig,
+    guide_layout: DlssRayReconstructionGuideLayout,
+    motion_vector_convention: MotionVectorConvention,
+    reset_pending: bool,
+    exposure_texture: ExposureTexture,
+    tone_mapper_type: DlssToneMapperType,
+    debug_options: DlssDebugOptions,
+    last_render: Option<Instant>,
+    device: Device,
+    sdk: Arc<Mutex<DlssSdk>>,
+    feature: *mut NVSDK_NGX_Handle,
+}
+
+impl DlssRayReconstructionContext {
+    /// Create a new [`DlssRayReconstructionContext`].
+    ///
+    /// Returns [`DlssError::FeatureNotSupported`] if ray reconstruction is not available on this system.
+    ///
+    /// This is an expensive operation. The resulting context should be cached, and only recreated when settings change.
+    pub fn new(
+        upscaled_resolution: UVec2,
+        perf_quality_mode: DlssPerfQualityMode,
+        feature_flags: DlssFeatureFlags,
+        guide_layout: DlssRayReconstructionGuideLayout,
+        sdk: Arc<Mutex<DlssSdk>>,
+        device: &Device,
+        queue: &Queue,
+    ) -> Result<Self, DlssError> {
+        let locked_sdk = sdk.lock().unwrap();
+
+        if !locked_sdk.is_ray_reconstruction_supported() {
+            return Err(DlssError::FeatureNotSupported);
+        }
+
+        let config = FeatureConfig::new(
+            &locked_sdk,
+            DlssFeature::RayReconstruction,
+            upscaled_resolution,
+            perf_quality_mode,
+            feature_flags,
+        )?;
+
+        let mut dlssd_create_params = NVSDK_NGX_DLSSD_Create_Params {
+            InDenoiseMode: NVSDK_NGX_DLSS_Denoise_Mode_NVSDK_NGX_DLSS_Denoise_Mode_DLUnified,
+            InRoughnessMode: guide_layout.roughness_mode.as_roughness_mode(),
+            InUseHWDepth: guide_layout.depth_type.as_depth_type(),
+            InWidth: config.optimal_render_resolution.x,
+            InHeight: config.optimal_render_resolution.y,
+            InTargetWidth: upscaled_resolution.x,
+            InTargetHeight: upscaled_resolution.y,
+            InPerfQualityValue: perf_quality_mode.as_perf_quality_value(upscaled_resolution),
+            InFeatureCreateFlags: feature_flags.as_flags(),
+            InEnableOutputSubrects: feature_flags.contains(DlssFeatureFlags::OutputSubrect),
+        };
+
+        let mut command_encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("dlss_ray_reconstruction_context_creation"),
+        });
+
+        let mut feature = ptr::null_mut();
+        unsafe {
+            let raw_device =
+                device.as_hal::<Vulkan, _, _>(|device| device.unwrap().raw_device().handle());
+            command_encoder
+                .as_hal_mut::<Vulkan, _, _>(|command_encoder| {
+                    check_ngx_result(
+                        "NGX_VULKAN_CREATE_DLSSD_EXT1",
+                        NGX_VULKAN_CREATE_DLSSD_EXT1(
+                            raw_device,
+                            command_encoder.unwrap().raw_handle(),
+                            1,
+                            1,
+                            &mut feature,
+                            locked_sdk.parameters,
+                            &mut dlssd_create_params,
+                        ),
+                    )
+                })
+                .map_err(|e| {
+                    e.with_context(config.error_context(Some(config.optimal_render_resolution)))
+                })?
+        }
+
+        queue.submit([command_encoder.finish()]);
+
+        Ok(Self {
+            config,
+            guide_layout,
+            motion_vector_convention: MotionVectorConvention::default(),
+            reset_pending: false,
+            exposure_texture: ExposureTexture::new(queue),
+            tone_mapper_type: DlssToneMapperType::default(),
+            debug_options: DlssDebugOptions::default(),
+            last_render: None,
+            device: device.clone(),
+            sdk: Arc::clone(&sdk),
+            feature,
+        })
+    }
+
+    /// Encode commands to render DLSS Ray Reconstruction.
+    pub fn render(
+        &mut self,
+        render_parameters: DlssRayReconstructionRenderParameters,
+        command_encoder: &mut CommandEncoder,
+        adapter: &Adapter,
+    ) -> Result<(), DlssError> {
+        render_parameters.validate(self.guide_layout)?;
+
+        self.exposure_texture
+            .update(&render_parameters.base.exposure, &self.device);
+        let render_parameters = DlssRayReconstructionRenderParameters {
+            base: DlssRenderParameters {
+                exposure: self
+                    .exposure_texture
+                    .resolve(render_parameters.base.exposure),
+                ..render_parameters.base
+            },
+            ..render_parameters
+        };
+        let base = &render_parameters.base;
+
+        let reset = base.reset || mem::take(&mut self.reset_pending);
+        let frame_time_delta = frame_time_delta(&mut self.last_render, base.frame_time_delta);
+        let debug_options = self.debug_options.effective();
+
+        let sdk = self.sdk.lock().unwrap();
+
+        let partial_texture_size = self.config.partial_texture_size(base);
+        let motion_vector_scale = self
+            .config
+            .motion_vector_scale(base, self.motion_vector_convention);
+
+        let mut exposure = ExposureResource::new(&base.exposure, adapter);
+        let mut guides = GuideResources::new(&base.guides, adapter);
+        let mut roughness = render_parameters
+            .roughness
+            .as_ref()
+            .map(|texture| texture.as_resource(adapter));
+        let mut specular_hit_distance = render_parameters
+            .specular_hit_distance
+            .as_ref()
+            .map(|texture| texture.as_resource(adapter));
+        let mut world_to_view = render_parameters.world_to_view.to_cols_array();
+        let mut view_to_clip = render_parameters.view_to_clip.to_cols_array();
+
+        let mut dlssd_eval_params = NVSDK_NGX_VK_DLSSD_Eval_Params {
+            pInDiffuseAlbedo: &mut render_parameters.diffuse_albedo.as_resource(adapter),
+            pInSpecularAlbedo: &mut render_parameters.specular_albedo.as_resource(adapter),
+            pInNormals: &mut render_parameters.normals.as_resource(adapter),
+            pInRoughness: resource_ptr(&mut roughness),
+            pInColor: &mut base.color.as_resource(adapter),
+            pInOutput: &mut base.dlss_output.as_resource(adapter),
+            pInDepth: &mut base.depth.as_resource(adapter),
+            pInMotionVectors: &mut base.motion_vectors.as_resource(adapter),
+            InJitterOffsetX: base.jitter_offset.x,
+            InJitterOffsetY: base.jitter_offset.y,
+            InRenderSubrectDimensions: NVSDK_NGX_Dimensions {
+                Width: partial_texture_size.x,
+                Height: partial_texture_size.y,
+            },
+            InReset: reset as _,
+            InMVScaleX: motion_vector_scale.x,
+            InMVScaleY: motion_vector_scale.y,
+            pInTransparencyMask: resource_ptr(&mut guides.transparency_mask),
+            pInExposureTexture: resource_ptr(&mut exposure.texture),
+            pInBiasCurrentColorMask: match &base.bias {
+                Some(bias) => &mut bias.as_resource(adapter),
+                None => ptr::null_mut(),
+            },
+            InPreExposure: exposure.pre_exposure,
+            InExposureScale: exposure.exposure_scale,
+            InIndicatorInvertXAxis: debug_options.invert_indicator_x_axis as _,
+            InIndicatorInvertYAxis: debug_options.invert_indicator_y_axis as _,
+            GBufferSurface: NVSDK_NGX_VK_GBuffer {
+                pInAttrib: guides.gbuffer.each_mut().map(resource_ptr),
+            },
+            InToneMapperType: self.tone_mapper_type.as_tone_mapper_type(),
+            pInMotionVectors3D: resource_ptr(&mut guides.motion_vectors_3d),
+            pInIsParticleMask: resource_ptr(&mut guides.particle_mask),
+            pInAnimatedTextureMask: resource_ptr(&mut guides.animated_texture_mask),
+            pInDepthHighRes: resource_ptr(&mut guides.depth_high_res),
+            pInPositionViewSpace: resource_ptr(&mut guides.position_view_space),
+            InFrameTimeDeltaInMsec: frame_time_delta.as_secs_f32() * 1000.0,
+            pInRayTracingHitDistance: resource_ptr(&mut guides.ray_tracing_hit_distance),
+            pInMotionVectorsReflections: resource_ptr(&mut guides.motion_vectors_reflections),
+            // glam's column-major layout matches the row-major, row vector convention NGX expects
+            pInWorldToViewMatrix: world_to_view.as_mut_ptr(),
+            pInViewToClipMatrix: view_to_clip.as_mut_ptr(),
+            pInSpecularHitDistance: resource_ptr(&mut specular_hit_distance),
+            // Unused inputs are null, and subrect bases are zero
+            ..unsafe { mem::zeroed() }
+        };
+
+        command_encoder.transition_resources(iter::empty(), render_parameters.barrier_list());
+        unsafe {
+            command_encoder.as_hal_mut::<Vulkan, _, _>(|command_encoder| {
+                check_ngx_result(
+                    "NGX_VULKAN_EVALUATE_DLSSD_EXT",
+                    NGX_VULKAN_EVALUATE_DLSSD_EXT(
+                        command_encoder.unwrap().raw_handle(),
+                        self.feature,
+                        sdk.parameters,
+                        &mut dlssd_eval_params,
+                    ),
+                )
+            })
+        }
+        .map_err(|e| e.with_context(self.config.error_context(Some(partial_texture_size))))
+    }
+
+    /// Reset temporal history on the next call to [`Self::render`], like [`DlssRenderParameters::reset`].
+    pub fn reset(&mut self) {
+        self.reset_pending = true;
+    }
+
+    /// Suggested subpixel camera jitter for a given frame.
+    ///
+    /// Uses a Halton(2, 3) sequence. See [`crate::JitterGenerator`] for other sequences.
+    pub fn suggested_jitter(&self, frame_number: u32, render_resolution: UVec2) -> Vec2 {
+        self.config
+            .suggested_jitter(frame_number, render_resolution)
+    }
+
+    /// Suggested mip bias for sampling textures.
+    pub fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
+        self.config.suggested_mip_bias(render_resolution)
+    }
+
+    /// The upscaled resolution DLSS will output at.
+    pub fn upscaled_resolution(&self) -> UVec2 {
+        self.config.upscaled_resolution
+    }
+
+    /// The resolution the camera should render at, pre-upscaling.
+    pub fn render_resolution(&self) -> UVec2 {
+        self.config.min_render_resolution
+    }
+
+    /// Like [`Self::render_resolution`], but returns a range of values for use with dynamic resolution scaling.
+    pub fn render_resolution_range(&self) -> RangeInclusive<UVec2> {
+        self.config.render_resolution_range()
+    }
+
+    /// The guide buffer layout this context was created with.
+    pub fn guide_layout(&self) -> DlssRayReconstructionGuideLayout {
+        self.guide_layout
+    }
+
+    /// See [`crate::DlssContext::set_motion_vector_convention`].
+    pub fn set_motion_vector_convention(&mut self, convention: MotionVectorConvention) {
+        self.motion_vector_convention = convention;
+    }
+
+    pub fn motion_vector_convention(&self) -> MotionVectorConvention {
+        self.motion_vector_convention
+    }
+
+    /// See [`crate::DlssContext::set_tone_mapper_type`].
+    pub fn set_tone_mapper_type(&mut self, tone_mapper_type: DlssToneMapperType) {
+        self.tone_mapper_type = tone_mapper_type;
+    }
+
+    pub fn tone_mapper_type(&self) -> DlssToneMapperType {
+        self.tone_mapper_type
+    }
+
+    /// See [`crate::DlssContext::set_debug_options`].
+    pub fn set_debug_options(&mut self, debug_options: DlssDebugOptions) {
+        self.debug_options = debug_options;
+    }
+
+    pub fn debug_options(&self) -> DlssDebugOptions {
+        self.debug_options
+    }
+}
+
+impl Drop for DlssRayReconstructionContext {
+    fn drop(&mut self) {
+        release_feature(&self.device, self.feature, "DlssRayReconstructionContext");
+    }
+}
+
+unsafe impl Send for DlssRayReconstructionContext {}
+unsafe impl Sync for DlssRayReconstructionContext {}
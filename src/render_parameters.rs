@@ -1,16 +1,21 @@
 use crate::{
     DlssError,
-    nvsdk_ngx::{NVSDK_NGX_Create_ImageView_Resource_VK, NVSDK_NGX_Resource_VK},
-};
-use ash::vk::{
-    ImageAspectFlags, ImageSubresourceRange, REMAINING_ARRAY_LAYERS, REMAINING_MIP_LEVELS,
+    nvsdk_ngx::{
+        GBUFFER_ATTRIBUTE_COUNT, NVSDK_NGX_Create_ImageView_Resource_VK, NVSDK_NGX_Resource_VK,
+    },
 };
+use ash::vk::{ImageAspectFlags, ImageSubresourceRange};
 use glam::{UVec2, Vec2};
+use std::time::Duration;
 use wgpu::{
-    Adapter, Texture, TextureTransition, TextureUsages, TextureUses, TextureView, hal::api::Vulkan,
+    Adapter, Texture, TextureAspect, TextureDimension, TextureFormat, TextureTransition,
+    TextureUsages, TextureUses, TextureView, TextureViewDescriptor, TextureViewDimension,
+    hal::api::Vulkan, wgt::TextureSelector,
 };
 
 /// Inputs and output resources needed for rendering DLSS.
+///
+/// Prefer [`Self::new`] over a struct literal, so that optional inputs added in the future do not need to be set.
 pub struct DlssRenderParameters<'a> {
     /// Main color view of your camera.
     pub color: DlssTexture<'a>,
@@ -22,6 +27,8 @@ pub struct DlssRenderParameters<'a> {
     pub exposure: DlssExposure<'a>,
     /// Optional per-pixel bias to make DLSS more reactive.
     pub bias: Option<DlssTexture<'a>>,
+    /// Optional additional inputs that can improve quality.
+    pub guides: DlssGuideTextures<'a>,
     /// The texture DLSS outputs to.
     pub dlss_output: DlssTexture<'a>,
     /// Whether DLSS should reset temporal history, useful for camera cuts.
@@ -32,20 +39,149 @@ pub struct DlssRenderParameters<'a> {
     // TODO: Allow configuring partial texture origins
     pub partial_texture_size: Option<UVec2>,
     /// Optional scaling factor to apply to the values contained within [`Self::motion_vectors`].
+    ///
+    /// Overrides the scale computed from [`crate::DlssContext::set_motion_vector_convention`].
     pub motion_vector_scale: Option<Vec2>,
+    /// Time elapsed since the previous frame.
+    ///
+    /// If `None`, the time between calls to [`crate::DlssContext::render`] is used.
+    pub frame_time_delta: Option<Duration>,
 }
 
 impl<'a> DlssRenderParameters<'a> {
+    /// Create render parameters from the required inputs, with every optional input unset.
+    pub fn new(
+        color: DlssTexture<'a>,
+        depth: DlssTexture<'a>,
+        motion_vectors: DlssTexture<'a>,
+        exposure: DlssExposure<'a>,
+        dlss_output: DlssTexture<'a>,
+    ) -> Self {
+        Self {
+            color,
+            depth,
+            motion_vectors,
+            exposure,
+            bias: None,
+            guides: DlssGuideTextures::default(),
+            dlss_output,
+            reset: false,
+            jitter_offset: Vec2::ZERO,
+            partial_texture_size: None,
+            motion_vector_scale: None,
+            frame_time_delta: None,
+        }
+    }
+
+    /// Set [`Self::guides`].
+    pub fn with_guides(mut self, guides: DlssGuideTextures<'a>) -> Self {
+        self.guides = guides;
+        self
+    }
+
+    /// Set [`Self::frame_time_delta`], instead of measuring it between calls to [`crate::DlssContext::render`].
+    pub fn with_frame_time_delta(mut self, frame_time_delta: Duration) -> Self {
+        self.frame_time_delta = Some(frame_time_delta);
+        self
+    }
+
+    /// Whether DLSS can read [`Self::depth`] directly from a texture view of the given format.
+    ///
+    /// Other depth formats can be converted using [`crate::DlssDepthCopyPass`].
+    pub fn supports_depth_format(format: TextureFormat) -> bool {
+        matches!(
+            format,
+            TextureFormat::Depth32Float
+                | TextureFormat::Depth24Plus
+                | TextureFormat::Depth24PlusStencil8
+                | TextureFormat::Depth32FloatStencil8
+                | TextureFormat::R32Float
+                | TextureFormat::R16Float
+        )
+    }
+
     pub(crate) fn validate(&self) -> Result<(), DlssError> {
-        // TODO
+        validate_depth("depth", &self.depth)?;
+        self.validate_guides()
+    }
+
+    fn validate_guides(&self) -> Result<(), DlssError> {
+        let render_size = self.color.size();
+        for (name, texture) in self.guides.named_textures() {
+            let expected_size = if name == "depth_high_res" {
+                validate_depth(name, texture)?;
+                self.dlss_output.size()
+            } else {
+                render_size
+            };
+
+            if texture.size() != expected_size {
+                return Err(DlssError::InvalidRenderParameters(format!(
+                    "guide texture {name} has size {}, but must have size {expected_size}",
+                    texture.size()
+                )));
+            }
+        }
+
         Ok(())
     }
 
+    /// Every texture referenced by these parameters, including [`Self::dlss_output`].
+    pub(crate) fn textures(&self) -> impl Iterator<Item = &DlssTexture<'a>> {
+        [
+            Some(&self.color),
+            Some(&self.depth),
+            Some(&self.motion_vectors),
+            match &self.exposure {
+                DlssExposure::Manual { exposure, .. } => Some(exposure),
+                DlssExposure::Value(_) | DlssExposure::Automatic => None,
+            },
+            self.bias.as_ref(),
+            Some(&self.dlss_output),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(self.guides.named_textures().map(|(_, texture)| texture))
+    }
+
+    /// Copy these parameters, replacing each texture with the result of `f`.
+    pub(crate) fn map_textures<'b>(
+        &self,
+        mut f: impl FnMut(&DlssTexture<'a>) -> DlssTexture<'b>,
+    ) -> DlssRenderParameters<'b> {
+        DlssRenderParameters {
+            color: f(&self.color),
+            depth: f(&self.depth),
+            motion_vectors: f(&self.motion_vectors),
+            exposure: match &self.exposure {
+                DlssExposure::Manual {
+                    exposure,
+                    exposure_scale,
+                    pre_exposure,
+                } => DlssExposure::Manual {
+                    exposure: f(exposure),
+                    exposure_scale: *exposure_scale,
+                    pre_exposure: *pre_exposure,
+                },
+                DlssExposure::Value(exposure) => DlssExposure::Value(*exposure),
+                DlssExposure::Automatic => DlssExposure::Automatic,
+            },
+            bias: self.bias.as_ref().map(&mut f),
+            guides: self.guides.map_textures(&mut f),
+            dlss_output: f(&self.dlss_output),
+            reset: self.reset,
+            jitter_offset: self.jitter_offset,
+            partial_texture_size: self.partial_texture_size,
+            motion_vector_scale: self.motion_vector_scale,
+            frame_time_delta: self.frame_time_delta,
+        }
+    }
+
     pub(crate) fn barrier_list(&self) -> impl Iterator<Item = TextureTransition<&'a Texture>> {
         fn resource_barrier<'a>(texture: &DlssTexture<'a>) -> TextureTransition<&'a Texture> {
             TextureTransition {
                 texture: texture.texture,
-                selector: None,
+                selector: Some(texture.selector()),
                 state: TextureUses::RESOURCE,
             }
         }
@@ -56,40 +192,314 @@ impl<'a> DlssRenderParameters<'a> {
             Some(resource_barrier(&self.motion_vectors)),
             match &self.exposure {
                 DlssExposure::Manual { exposure, .. } => Some(resource_barrier(exposure)),
-                DlssExposure::Automatic => None,
+                DlssExposure::Value(_) | DlssExposure::Automatic => None,
             },
             self.bias.as_ref().map(resource_barrier),
             Some(TextureTransition {
                 texture: self.dlss_output.texture,
-                selector: None,
+                selector: Some(self.dlss_output.selector()),
                 state: TextureUses::STORAGE_READ_WRITE,
             }),
         ]
         .into_iter()
         .flatten()
+        .chain(
+            self.guides
+                .named_textures()
+                .map(|(_, texture)| resource_barrier(texture)),
+        )
+    }
+}
+
+fn validate_depth(name: &str, depth: &DlssTexture) -> Result<(), DlssError> {
+    let texture_format = depth.texture.format();
+
+    if texture_format.is_combined_depth_stencil_format()
+        && depth.aspect() != TextureAspect::DepthOnly
+    {
+        return Err(DlssError::InvalidRenderParameters(format!(
+            "{name} texture has combined depth-stencil format {texture_format:?}, but the view uses aspect {:?} instead of TextureAspect::DepthOnly",
+            depth.aspect()
+        )));
+    }
+    if !DlssRenderParameters::supports_depth_format(texture_format) {
+        return Err(DlssError::InvalidRenderParameters(format!(
+            "{name} texture format {texture_format:?} is not supported by DLSS, use DlssDepthCopyPass to convert it to R32Float"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Optional guide textures for [`DlssRenderParameters`].
+///
+/// All guides are at the same resolution as [`DlssRenderParameters::color`], except for [`Self::depth_high_res`].
+#[derive(Clone, Copy, Default)]
+pub struct DlssGuideTextures<'a> {
+    /// Mask of transparent surfaces that are not represented in the motion vectors or depth.
+    pub transparency_mask: Option<DlssTexture<'a>>,
+    /// Mask of particles, which DLSS will treat as more reactive.
+    pub particle_mask: Option<DlssTexture<'a>>,
+    /// Mask of surfaces with animated textures, such as screens or scrolling water.
+    pub animated_texture_mask: Option<DlssTexture<'a>>,
+    /// Depth buffer at the upscaled resolution, the same size as [`DlssRenderParameters::dlss_output`].
+    pub depth_high_res: Option<DlssTexture<'a>>,
+    /// View-space position of each pixel.
+    pub position_view_space: Option<DlssTexture<'a>>,
+    /// Motion vectors including motion along the depth axis.
+    pub motion_vectors_3d: Option<DlssTexture<'a>>,
+    /// Distance from each pixel to its ray-traced reflection or shadow hit.
+    pub ray_tracing_hit_distance: Option<DlssTexture<'a>>,
+    /// Motion vectors of surfaces seen in reflections.
+    pub motion_vectors_reflections: Option<DlssTexture<'a>>,
+    /// GBuffer attributes, indexed by [`crate::DlssGBufferAttribute`] (e.g. `gbuffer[DlssGBufferAttribute::Normals as usize]`).
+    pub gbuffer: [Option<DlssTexture<'a>>; GBUFFER_ATTRIBUTE_COUNT],
+}
+
+impl<'a> DlssGuideTextures<'a> {
+    /// Every guide texture that is set, with its field name.
+    pub(crate) fn named_textures(&self) -> impl Iterator<Item = (&'static str, &DlssTexture<'a>)> {
+        [
+            ("transparency_mask", &self.transparency_mask),
+            ("particle_mask", &self.particle_mask),
+            ("animated_texture_mask", &self.animated_texture_mask),
+            ("depth_high_res", &self.depth_high_res),
+            ("position_view_space", &self.position_view_space),
+            ("motion_vectors_3d", &self.motion_vectors_3d),
+            ("ray_tracing_hit_distance", &self.ray_tracing_hit_distance),
+            (
+                "motion_vectors_reflections",
+                &self.motion_vectors_reflections,
+            ),
+        ]
+        .into_iter()
+        .chain(self.gbuffer.iter().map(|texture| ("gbuffer", texture)))
+        .filter_map(|(name, texture)| Some((name, texture.as_ref()?)))
+    }
+
+    fn map_textures<'b>(
+        &self,
+        f: &mut impl FnMut(&DlssTexture<'a>) -> DlssTexture<'b>,
+    ) -> DlssGuideTextures<'b> {
+        DlssGuideTextures {
+            transparency_mask: self.transparency_mask.as_ref().map(&mut *f),
+            particle_mask: self.particle_mask.as_ref().map(&mut *f),
+            animated_texture_mask: self.animated_texture_mask.as_ref().map(&mut *f),
+            depth_high_res: self.depth_high_res.as_ref().map(&mut *f),
+            position_view_space: self.position_view_space.as_ref().map(&mut *f),
+            motion_vectors_3d: self.motion_vectors_3d.as_ref().map(&mut *f),
+            ray_tracing_hit_distance: self.ray_tracing_hit_distance.as_ref().map(&mut *f),
+            motion_vectors_reflections: self.motion_vectors_reflections.as_ref().map(&mut *f),
+            gbuffer: self
+                .gbuffer
+                .each_ref()
+                .map(|texture| texture.as_ref().map(&mut *f)),
+        }
     }
 }
 
 /// Camera exposure used by DLSS.
+#[derive(Clone, Copy)]
 pub enum DlssExposure<'a> {
-    /// Exposure controlled by the application.
+    /// Exposure controlled by the application, read from a 1x1 texture.
+    ///
+    /// Useful for exposure computed on the GPU.
     Manual {
         exposure: DlssTexture<'a>,
         exposure_scale: Option<f32>,
         pre_exposure: Option<f32>,
     },
+    /// Exposure controlled by the application, as a single value.
+    ///
+    /// The context uploads the value into a 1x1 texture it owns with [`wgpu::Queue::write_texture`] whenever it changes,
+    /// so it takes effect at the next submission.
+    Value(f32),
     /// Auto-exposure handled by DLSS.
     Automatic,
 }
 
 /// Wrapper for a texture(view) used by [`DlssRenderParameters`].
+///
+/// The format, aspect, size, and subresources DLSS accesses are derived from the descriptor the view was created with,
+/// which wgpu does not expose, so it must be passed to [`Self::with_view_descriptor`] for views that are not created
+/// with [`TextureViewDescriptor::default`].
+#[derive(Clone, Copy)]
 pub struct DlssTexture<'a> {
-    pub texture: &'a Texture,
-    pub view: &'a TextureView,
+    pub(crate) texture: &'a Texture,
+    pub(crate) view: &'a TextureView,
+    /// If `None`, the view was created with [`TextureViewDescriptor::default`].
+    pub(crate) view_descriptor: Option<&'a TextureViewDescriptor<'a>>,
 }
 
 impl<'a> DlssTexture<'a> {
+    /// Create a [`DlssTexture`] for a view created with [`TextureViewDescriptor::default`].
+    pub fn new(texture: &'a Texture, view: &'a TextureView) -> Self {
+        Self {
+            texture,
+            view,
+            view_descriptor: None,
+        }
+    }
+
+    /// Create a [`DlssTexture`] for a view created with the given descriptor.
+    ///
+    /// Returns [`DlssError::InvalidRenderParameters`] if the descriptor does not describe a valid view of `texture`.
+    pub fn with_view_descriptor(
+        texture: &'a Texture,
+        view: &'a TextureView,
+        view_descriptor: &'a TextureViewDescriptor<'a>,
+    ) -> Result<Self, DlssError> {
+        let texture_format = texture.format();
+        let Some(aspect_format) = texture_format.aspect_specific_format(view_descriptor.aspect)
+        else {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "view descriptor aspect {:?} is not valid for texture format {texture_format:?}",
+                view_descriptor.aspect
+            )));
+        };
+        if let Some(format) = view_descriptor.format
+            && format != texture_format
+            && format.remove_srgb_suffix() != aspect_format.remove_srgb_suffix()
+        {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "view descriptor format {format:?} is not compatible with texture format {texture_format:?}"
+            )));
+        }
+
+        let dlss_texture = Self {
+            texture,
+            view,
+            view_descriptor: Some(view_descriptor),
+        };
+        let selector = dlss_texture.selector();
+        if selector.mips.is_empty() || selector.mips.end > texture.mip_level_count() {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "view descriptor selects mip levels {:?}, but the texture has {} mip levels",
+                selector.mips,
+                texture.mip_level_count()
+            )));
+        }
+        let layer_count = match texture.dimension() {
+            TextureDimension::D3 => 1,
+            TextureDimension::D1 | TextureDimension::D2 => texture.depth_or_array_layers(),
+        };
+        if selector.layers.is_empty() || selector.layers.end > layer_count {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "view descriptor selects array layers {:?}, but the texture has {layer_count} array layers",
+                selector.layers
+            )));
+        }
+
+        Ok(dlss_texture)
+    }
+
+    pub fn texture(&self) -> &'a Texture {
+        self.texture
+    }
+
+    pub fn view(&self) -> &'a TextureView {
+        self.view
+    }
+
+    /// The descriptor given to [`Self::with_view_descriptor`], if any.
+    pub fn view_descriptor(&self) -> Option<&'a TextureViewDescriptor<'a>> {
+        self.view_descriptor
+    }
+
+    /// The format of the view.
+    pub(crate) fn format(&self) -> TextureFormat {
+        let texture_format = self.texture.format();
+        self.view_descriptor
+            .and_then(|descriptor| descriptor.format)
+            .unwrap_or_else(|| {
+                texture_format
+                    .aspect_specific_format(self.aspect())
+                    .unwrap_or(texture_format)
+            })
+    }
+
+    /// The aspect of the texture selected by the view.
+    pub(crate) fn aspect(&self) -> TextureAspect {
+        self.view_descriptor
+            .map(|descriptor| descriptor.aspect)
+            .unwrap_or_default()
+    }
+
+    /// The size of the base mip level of the view.
+    pub(crate) fn size(&self) -> UVec2 {
+        let size = self
+            .texture
+            .size()
+            .mip_level_size(self.selector().mips.start, self.texture.dimension());
+        UVec2::new(size.width, size.height)
+    }
+
+    /// The mip levels and array layers selected by the view.
+    pub(crate) fn selector(&self) -> TextureSelector {
+        let descriptor = self.view_descriptor.cloned().unwrap_or_default();
+
+        let dimension = descriptor
+            .dimension
+            .unwrap_or(match self.texture.dimension() {
+                TextureDimension::D1 => TextureViewDimension::D1,
+                TextureDimension::D2 if self.texture.depth_or_array_layers() == 1 => {
+                    TextureViewDimension::D2
+                }
+                TextureDimension::D2 => TextureViewDimension::D2Array,
+                TextureDimension::D3 => TextureViewDimension::D3,
+            });
+
+        let mip_level_count = descriptor.mip_level_count.unwrap_or(
+            self.texture
+                .mip_level_count()
+                .saturating_sub(descriptor.base_mip_level),
+        );
+        let array_layer_count = descriptor.array_layer_count.unwrap_or(match dimension {
+            TextureViewDimension::D1 | TextureViewDimension::D2 | TextureViewDimension::D3 => 1,
+            TextureViewDimension::Cube => 6,
+            TextureViewDimension::D2Array | TextureViewDimension::CubeArray => self
+                .texture
+                .depth_or_array_layers()
+                .saturating_sub(descriptor.base_array_layer),
+        });
+
+        TextureSelector {
+            mips: descriptor.base_mip_level..(descriptor.base_mip_level + mip_level_count),
+            layers: descriptor.base_array_layer..(descriptor.base_array_layer + array_layer_count),
+        }
+    }
+
+    /// Whether the view can be used as a storage texture.
+    pub(crate) fn is_storage(&self) -> bool {
+        self.view_descriptor
+            .and_then(|descriptor| descriptor.usage)
+            .filter(|usage| !usage.is_empty())
+            .unwrap_or(self.texture.usage())
+            .contains(TextureUsages::STORAGE_BINDING)
+    }
+
     pub(crate) fn as_resource(&self, adapter: &Adapter) -> NVSDK_NGX_Resource_VK {
+        let format = self.format();
+        let texture_format = self.texture.format();
+        // wgpu creates depth or stencil only views of combined depth-stencil textures using the combined format
+        let raw_format = if format.is_depth_stencil_component(texture_format) {
+            texture_format
+        } else {
+            format
+        };
+
+        let aspect_mask = match self.aspect() {
+            TextureAspect::All if format.has_color_aspect() => ImageAspectFlags::COLOR,
+            TextureAspect::All | TextureAspect::DepthOnly => ImageAspectFlags::DEPTH,
+            TextureAspect::StencilOnly => ImageAspectFlags::STENCIL,
+            TextureAspect::Plane0 => ImageAspectFlags::PLANE_0,
+            TextureAspect::Plane1 => ImageAspectFlags::PLANE_1,
+            TextureAspect::Plane2 => ImageAspectFlags::PLANE_2,
+        };
+
+        let selector = self.selector();
+        let size = self.size();
+
         unsafe {
             NVSDK_NGX_Create_ImageView_Resource_VK(
                 self.view
@@ -97,26 +507,18 @@ impl<'a> DlssTexture<'a> {
                 self.texture
                     .as_hal::<Vulkan, _, _>(|t| t.unwrap().raw_handle()),
                 ImageSubresourceRange {
-                    aspect_mask: if self.texture.format().has_color_aspect() {
-                        ImageAspectFlags::COLOR
-                    } else {
-                        ImageAspectFlags::DEPTH
-                    },
-                    base_mip_level: 0,
-                    level_count: REMAINING_MIP_LEVELS,
-                    base_array_layer: 0,
-                    layer_count: REMAINING_ARRAY_LAYERS,
+                    aspect_mask,
+                    base_mip_level: selector.mips.start,
+                    level_count: selector.mips.len() as u32,
+                    base_array_layer: selector.layers.start,
+                    layer_count: selector.layers.len() as u32,
                 },
                 adapter.as_hal::<Vulkan, _, _>(|adapter| {
-                    adapter
-                        .unwrap()
-                        .texture_format_as_raw(self.texture.format())
+                    adapter.unwrap().texture_format_as_raw(raw_format)
                 }),
-                self.texture.width(),
-                self.texture.height(),
-                self.texture
-                    .usage()
-                    .contains(TextureUsages::STORAGE_BINDING),
+                size.x,
+                size.y,
+                self.is_storage(),
             )
         }
     }
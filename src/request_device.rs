@@ -1,5 +1,5 @@
 use crate::{
-    feature_info::with_feature_info,
+    feature_info::{DlssFeature, with_feature_info},
     nvsdk_ngx::{
         DlssError, NVSDK_NGX_VULKAN_GetFeatureDeviceExtensionRequirements, check_ngx_result,
     },
@@ -13,10 +13,13 @@ use wgpu::{Adapter, Device, DeviceDescriptor, Queue, hal::api::Vulkan};
 
 /// Creates a wgpu [`Device`] and [`Queue`] with the extensions required for DLSS.
 ///
-/// If the system does not support DLSS, it will return [`DlssError::FeatureNotSupported`] wrapped in [`RequestDeviceError::DlssError`].
+/// If the system does not support DLSS, it will return a [`RequestDeviceError::DlssError`] for which
+/// [`DlssError::is_feature_not_supported`] is true.
 ///
 /// When DLSS is not supported, users should fallback to using [`wgpu::Adapter::request_device`].
 ///
+/// The extensions needed by DLSS Ray Reconstruction are also enabled if the system supports it, but it is not required.
+///
 /// The provided [`Adapter`] must be using the Vulkan backend.
 pub fn request_device(
     project_id: Uuid,
@@ -35,10 +38,24 @@ pub fn request_device(
                     raw_adapter.required_device_extensions(device_descriptor.required_features);
                 enabled_extensions.extend(dlss_device_extensions(
                     project_id,
+                    DlssFeature::SuperResolution,
                     raw_adapter,
                     raw_instance.handle(),
                     raw_physical_device,
                 )?);
+                if let Ok(extensions) = dlss_device_extensions(
+                    project_id,
+                    DlssFeature::RayReconstruction,
+                    raw_adapter,
+                    raw_instance.handle(),
+                    raw_physical_device,
+                ) {
+                    for extension in extensions {
+                        if !enabled_extensions.contains(&extension) {
+                            enabled_extensions.push(extension);
+                        }
+                    }
+                }
                 let mut enabled_phd_features = raw_adapter.physical_device_features(
                     &enabled_extensions,
                     device_descriptor.required_features,
@@ -79,21 +96,25 @@ pub fn request_device(
 
 fn dlss_device_extensions(
     project_id: Uuid,
+    feature: DlssFeature,
     raw_adapter: &wgpu::hal::vulkan::Adapter,
     raw_instance: Instance,
     raw_physical_device: PhysicalDevice,
 ) -> Result<impl Iterator<Item = &'static CStr>, DlssError> {
-    with_feature_info(project_id, |feature_info| unsafe {
+    with_feature_info(project_id, feature, |feature_info| unsafe {
         let mut dlss_device_extensions = ptr::null_mut();
         let mut dlss_device_extension_count = 0;
 
-        check_ngx_result(NVSDK_NGX_VULKAN_GetFeatureDeviceExtensionRequirements(
-            raw_instance,
-            raw_physical_device,
-            feature_info,
-            &mut dlss_device_extension_count,
-            &mut dlss_device_extensions,
-        ))?;
+        check_ngx_result(
+            "NVSDK_NGX_VULKAN_GetFeatureDeviceExtensionRequirements",
+            NVSDK_NGX_VULKAN_GetFeatureDeviceExtensionRequirements(
+                raw_instance,
+                raw_physical_device,
+                feature_info,
+                &mut dlss_device_extension_count,
+                &mut dlss_device_extensions,
+            ),
+        )?;
 
         let dlss_device_extensions =
             slice::from_raw_parts(dlss_device_extensions, dlss_device_extension_count as usize);
@@ -1,4 +1,7 @@
-use crate::{feature_info::with_feature_info, nvsdk_ngx::*};
+use crate::{
+    feature_info::{DlssFeature, with_feature_info},
+    nvsdk_ngx::*,
+};
 use std::{
     ptr,
     sync::{Arc, Mutex},
@@ -11,6 +14,7 @@ use wgpu::{Device, hal::api::Vulkan};
 pub struct DlssSdk {
     pub(crate) parameters: *mut NVSDK_NGX_Parameter,
     pub(crate) device: Device,
+    ray_reconstruction_supported: bool,
 }
 
 impl DlssSdk {
@@ -27,59 +31,97 @@ impl DlssSdk {
                 let shared_instance = device.shared_instance();
                 let raw_instance = shared_instance.raw_instance();
 
-                with_feature_info(project_id, |feature_info| {
-                    check_ngx_result(NVSDK_NGX_VULKAN_Init_with_ProjectID(
-                        feature_info.Identifier.v.ProjectDesc.ProjectId,
-                        NVSDK_NGX_EngineType_NVSDK_NGX_ENGINE_TYPE_CUSTOM,
-                        feature_info.Identifier.v.ProjectDesc.EngineVersion,
-                        feature_info.ApplicationDataPath,
-                        raw_instance.handle(),
-                        device.raw_physical_device(),
-                        device.raw_device().handle(),
-                        shared_instance.entry().static_fn().get_instance_proc_addr,
-                        raw_instance.fp_v1_0().get_device_proc_addr,
-                        feature_info.FeatureInfo,
-                        NVSDK_NGX_Version_NVSDK_NGX_Version_API,
-                    ))
+                with_feature_info(project_id, DlssFeature::SuperResolution, |feature_info| {
+                    check_ngx_result(
+                        "NVSDK_NGX_VULKAN_Init_with_ProjectID",
+                        NVSDK_NGX_VULKAN_Init_with_ProjectID(
+                            feature_info.Identifier.v.ProjectDesc.ProjectId,
+                            NVSDK_NGX_EngineType_NVSDK_NGX_ENGINE_TYPE_CUSTOM,
+                            feature_info.Identifier.v.ProjectDesc.EngineVersion,
+                            feature_info.ApplicationDataPath,
+                            raw_instance.handle(),
+                            device.raw_physical_device(),
+                            device.raw_device().handle(),
+                            shared_instance.entry().static_fn().get_instance_proc_addr,
+                            raw_instance.fp_v1_0().get_device_proc_addr,
+                            feature_info.FeatureInfo,
+                            NVSDK_NGX_Version_NVSDK_NGX_Version_API,
+                        ),
+                    )
                 })?;
 
-                check_ngx_result(NVSDK_NGX_VULKAN_GetCapabilityParameters(&mut parameters))
+                check_ngx_result(
+                    "NVSDK_NGX_VULKAN_GetCapabilityParameters",
+                    NVSDK_NGX_VULKAN_GetCapabilityParameters(&mut parameters),
+                )
             })?;
 
             let mut dlss_supported = 0;
-            let result = check_ngx_result(NVSDK_NGX_Parameter_GetI(
-                parameters,
-                NVSDK_NGX_Parameter_SuperSampling_Available.as_ptr().cast(),
-                &mut dlss_supported,
-            ));
+            let result = check_ngx_result(
+                "NVSDK_NGX_Parameter_GetI",
+                NVSDK_NGX_Parameter_GetI(
+                    parameters,
+                    NVSDK_NGX_Parameter_SuperSampling_Available.as_ptr().cast(),
+                    &mut dlss_supported,
+                ),
+            );
             if result.is_err() {
-                check_ngx_result(NVSDK_NGX_VULKAN_DestroyParameters(parameters))?;
+                check_ngx_result(
+                    "NVSDK_NGX_VULKAN_DestroyParameters",
+                    NVSDK_NGX_VULKAN_DestroyParameters(parameters),
+                )?;
                 result?;
             }
             if dlss_supported == 0 {
-                check_ngx_result(NVSDK_NGX_VULKAN_DestroyParameters(parameters))?;
+                check_ngx_result(
+                    "NVSDK_NGX_VULKAN_DestroyParameters",
+                    NVSDK_NGX_VULKAN_DestroyParameters(parameters),
+                )?;
                 return Err(DlssError::FeatureNotSupported);
             }
 
-            Ok(Arc::new(Mutex::new(Self { parameters, device })))
+            // Ray reconstruction is optional, so failing to query it only means it is unsupported
+            let mut ray_reconstruction_supported = 0;
+            let _ = NVSDK_NGX_Parameter_GetI(
+                parameters,
+                NVSDK_NGX_Parameter_SuperSamplingDenoising_Available
+                    .as_ptr()
+                    .cast(),
+                &mut ray_reconstruction_supported,
+            );
+
+            Ok(Arc::new(Mutex::new(Self {
+                parameters,
+                device,
+                ray_reconstruction_supported: ray_reconstruction_supported != 0,
+            })))
         }
     }
 
     /// Returns the number of bytes of VRAM allocated by DLSS.
     pub fn get_vram_allocated_bytes(&mut self) -> Result<u64, DlssError> {
         let mut vram_allocated_bytes = 0;
-        check_ngx_result(unsafe {
+        check_ngx_result("NGX_DLSS_GET_STATS", unsafe {
             NGX_DLSS_GET_STATS(self.parameters, &mut vram_allocated_bytes)
         })?;
         Ok(vram_allocated_bytes)
     }
+
+    /// Whether DLSS Ray Reconstruction is available, see [`crate::DlssRayReconstructionContext`].
+    ///
+    /// Also requires the device to have been created by [`crate::request_device`], which enables the extensions it needs.
+    pub fn is_ray_reconstruction_supported(&self) -> bool {
+        self.ray_reconstruction_supported
+    }
 }
 
 fn check_for_updates(project_id: Uuid) {
     thread::spawn(move || {
-        with_feature_info(project_id, |feature_info| unsafe {
-            NVSDK_NGX_UpdateFeature(&feature_info.Identifier, feature_info.FeatureID);
-        });
+        for feature in DlssFeature::ALL {
+            with_feature_info(project_id, feature, |feature_info| unsafe {
+                NVSDK_NGX_UpdateFeature(&feature_info.Identifier, feature_info.FeatureID);
+            });
+        }
     });
 }
 
@@ -93,10 +135,16 @@ impl Drop for DlssSdk {
                     .device_wait_idle()
                     .expect("Failed to wait for idle device when destroying DlssSdk");
 
-                check_ngx_result(NVSDK_NGX_VULKAN_DestroyParameters(self.parameters))
-                    .expect("Failed to destroy DlssSdk parameters");
-                check_ngx_result(NVSDK_NGX_VULKAN_Shutdown1(device.handle()))
-                    .expect("Failed to destroy DlssSdk");
+                check_ngx_result(
+                    "NVSDK_NGX_VULKAN_DestroyParameters",
+                    NVSDK_NGX_VULKAN_DestroyParameters(self.parameters),
+                )
+                .expect("Failed to destroy DlssSdk parameters");
+                check_ngx_result(
+                    "NVSDK_NGX_VULKAN_Shutdown1",
+                    NVSDK_NGX_VULKAN_Shutdown1(device.handle()),
+                )
+                .expect("Failed to destroy DlssSdk");
             });
         }
     }
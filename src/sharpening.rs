@@ -0,0 +1,129 @@
+use crate::{
+    DlssError, DlssTexture,
+    compute_pass::{
+        UniformValue, create_compute_pipeline, create_shader_module, create_uniform_buffer,
+        dispatch, shader_source_with_output_format, storage_texture_entry, texture_entry,
+        uniform_entry,
+    },
+};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindingResource, CommandEncoder, ComputePipeline, Device, TextureFormat, TextureSampleType,
+};
+
+/// Compute pass that applies contrast adaptive sharpening (CAS), e.g. to the output of DLSS.
+///
+/// DLSS no longer sharpens its output. See [`crate::DlssContext::set_sharpness`] to run this pass as part of DLSS.
+pub struct DlssSharpeningPass {
+    /// Pipeline for the current output format.
+    pipeline: Option<(TextureFormat, BindGroupLayout, ComputePipeline)>,
+}
+
+impl DlssSharpeningPass {
+    pub fn new() -> Self {
+        Self { pipeline: None }
+    }
+
+    /// Encode commands to sharpen `input` into `output`, which must have the same size.
+    ///
+    /// `sharpness` is between 0.0 and 1.0. With `hdr`, colors outside of the 0.0 to 1.0 range are supported.
+    /// Alpha is copied from `input` unchanged.
+    ///
+    /// `output` must use one of the `Rgba8Unorm`, `Rgba16Float` or `Rgba32Float` formats.
+    pub fn encode(
+        &mut self,
+        input: &DlssTexture,
+        output: &DlssTexture,
+        sharpness: f32,
+        hdr: bool,
+        command_encoder: &mut CommandEncoder,
+        device: &Device,
+    ) -> Result<(), DlssError> {
+        let size = output.size();
+        if input.size() != size {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "sharpening input size {} does not match output size {size}",
+                input.size()
+            )));
+        }
+
+        let output_format = output.format();
+        if self
+            .pipeline
+            .as_ref()
+            .is_none_or(|(format, ..)| *format != output_format)
+        {
+            self.pipeline = Some(create_pipeline(output_format, device)?);
+        }
+        let (_, bind_group_layout, pipeline) = self.pipeline.as_ref().unwrap();
+
+        let peak = -1.0 / (8.0 - 3.0 * sharpness.clamp(0.0, 1.0));
+        let uniforms = [peak.uniform_words(), (hdr as u32).uniform_words()].concat();
+        let uniform_buffer = create_uniform_buffer(device, "dlss_sharpening", &uniforms);
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("dlss_sharpening"),
+            layout: bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(input.view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(output.view),
+                },
+            ],
+        });
+        dispatch(
+            command_encoder,
+            "dlss_sharpening",
+            pipeline,
+            &bind_group,
+            size,
+        );
+
+        Ok(())
+    }
+}
+
+impl Default for DlssSharpeningPass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn create_pipeline(
+    output_format: TextureFormat,
+    device: &Device,
+) -> Result<(TextureFormat, BindGroupLayout, ComputePipeline), DlssError> {
+    let source = shader_source_with_output_format(include_str!("sharpening.wgsl"), output_format)
+        .ok_or_else(|| {
+        DlssError::InvalidRenderParameters(format!(
+            "output format {output_format:?} is not supported by DlssSharpeningPass"
+        ))
+    })?;
+    let shader_module = create_shader_module(device, "dlss_sharpening", &source);
+
+    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("dlss_sharpening"),
+        entries: &[
+            uniform_entry(0),
+            texture_entry(1, TextureSampleType::Float { filterable: false }),
+            storage_texture_entry(2, output_format),
+        ],
+    });
+    let pipeline = create_compute_pipeline(
+        device,
+        "dlss_sharpening",
+        &shader_module,
+        "sharpen",
+        &bind_group_layout,
+    );
+
+    Ok((output_format, bind_group_layout, pipeline))
+}
@@ -0,0 +1,64 @@
+// Replaced at runtime to match the format of the output texture
+alias OutputTexture = texture_storage_2d<rgba16float, write>;
+
+struct Uniforms {
+    // Negative weight of the cross neighbors at full amplitude, from -1/8 (weakest) to -1/5 (strongest)
+    peak: f32,
+    hdr: u32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var input: texture_2d<f32>;
+@group(0) @binding(2) var output: OutputTexture;
+
+fn max3(c: vec3<f32>) -> f32 {
+    return max(c.r, max(c.g, c.b));
+}
+
+fn load_input(pixel: vec2<i32>) -> vec3<f32> {
+    let clamped = clamp(pixel, vec2(0), vec2<i32>(textureDimensions(input)) - 1);
+    let color = textureLoad(input, clamped, 0).rgb;
+    // CAS expects colors in the 0.0 to 1.0 range, so HDR colors are sharpened in a reversibly tonemapped space
+    return select(color, color / (1.0 + max3(color)), uniforms.hdr != 0u);
+}
+
+fn to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(c, c / max(1.0 - max3(c), 1e-4), uniforms.hdr != 0u);
+}
+
+// Contrast adaptive sharpening, following AMD FidelityFX CAS without scaling
+@compute @workgroup_size(8, 8, 1)
+fn sharpen(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let output_size = textureDimensions(output);
+    if any(global_id.xy >= output_size) {
+        return;
+    }
+    let pixel = vec2<i32>(global_id.xy);
+
+    // a b c
+    // d e f
+    // g h i
+    let a = load_input(pixel + vec2(-1, -1));
+    let b = load_input(pixel + vec2(0, -1));
+    let c = load_input(pixel + vec2(1, -1));
+    let d = load_input(pixel + vec2(-1, 0));
+    let e = load_input(pixel);
+    let f = load_input(pixel + vec2(1, 0));
+    let g = load_input(pixel + vec2(-1, 1));
+    let h = load_input(pixel + vec2(0, 1));
+    let i = load_input(pixel + vec2(1, 1));
+
+    // Soft minimum and maximum, summed over the cross and the full 3x3 neighborhood
+    let cross_min = min(min(min(b, d), min(e, f)), h);
+    let cross_max = max(max(max(b, d), max(e, f)), h);
+    let soft_min = cross_min + min(cross_min, min(min(a, c), min(g, i)));
+    let soft_max = cross_max + max(cross_max, max(max(a, c), max(g, i)));
+
+    // Sharpen less where the neighborhood is close to clipping, so that no new extremes are introduced
+    let amplitude = sqrt(saturate(min(soft_min, 2.0 - soft_max) / max(soft_max, vec3(1e-5))));
+    let weight = amplitude * uniforms.peak;
+
+    let sharpened = ((b + d + f + h) * weight + e) / (1.0 + 4.0 * weight);
+    let alpha = textureLoad(input, pixel, 0).a;
+    textureStore(output, global_id.xy, vec4(to_linear(saturate(sharpened)), alpha));
+}
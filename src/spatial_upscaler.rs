@@ -0,0 +1,491 @@
+use crate::{
+    DlssError, DlssFeatureFlags, DlssPerfQualityMode, DlssRenderParameters, Upscaler,
+    compute_pass::{
+        ScratchTexture, UniformValue, create_compute_pipeline, create_shader_module,
+        create_uniform_buffer, dispatch, shader_source_with_output_format, storage_texture_entry,
+        texture_entry, uniform_entry,
+    },
+};
+use glam::{UVec2, Vec2};
+use std::ops::RangeInclusive;
+use wgpu::{
+    Adapter, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindingResource, Buffer, CommandEncoder, ComputePipeline, Device, TextureFormat,
+    TextureSampleType, TextureUsages,
+};
+
+/// Spatial upscaler and sharpener, for use when DLSS is not supported.
+///
+/// Scaling uses a 6-tap Lanczos filter. Sharpening is modeled on NVIDIA Image Scaling (NIS), with its edge detection,
+/// directional unsharp mask and luma-dependent limits, but does not use NIS's coefficient tables, so results differ
+/// from NIS itself.
+///
+/// With [`DlssPerfQualityMode::Dlaa`], no scaling is done and the upscaler is used as a standalone sharpening pass.
+///
+/// Only [`DlssRenderParameters::color`], [`DlssRenderParameters::dlss_output`] and
+/// [`DlssRenderParameters::partial_texture_size`] are used. [`DlssRenderParameters::dlss_output`] must use one of the
+/// `Rgba8Unorm`, `Rgba16Float` or `Rgba32Float` formats.
+pub struct SpatialUpscaler {
+    upscaled_resolution: UVec2,
+    render_resolution: UVec2,
+    hdr: bool,
+    sharpness: f32,
+    scale_pipeline: ComputePipeline,
+    scale_bind_group_layout: BindGroupLayout,
+    /// Pipeline for the current output format.
+    sharpen_pipeline: Option<(TextureFormat, BindGroupLayout, ComputePipeline)>,
+    scaled: ScratchTexture,
+    device: Device,
+}
+
+impl SpatialUpscaler {
+    /// Create a new [`SpatialUpscaler`].
+    ///
+    /// The render resolution is chosen from `perf_quality_mode` using the same ratios as DLSS.
+    /// Of `feature_flags`, only [`DlssFeatureFlags::HighDynamicRange`] is used.
+    pub fn new(
+        upscaled_resolution: UVec2,
+        perf_quality_mode: DlssPerfQualityMode,
+        feature_flags: DlssFeatureFlags,
+        device: &Device,
+    ) -> Self {
+        let shader_module = create_shader_module(
+            device,
+            "spatial_upscaler_scale",
+            include_str!("spatial_upscaler.wgsl"),
+        );
+        let scale_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("spatial_upscaler_scale"),
+            entries: &[
+                uniform_entry(0),
+                texture_entry(1, TextureSampleType::Float { filterable: false }),
+                storage_texture_entry(2, TextureFormat::Rgba16Float),
+            ],
+        });
+
+        Self {
+            upscaled_resolution,
+            render_resolution: perf_quality_mode.render_resolution(upscaled_resolution),
+            hdr: feature_flags.contains(DlssFeatureFlags::HighDynamicRange),
+            sharpness: 0.5,
+            scale_pipeline: create_compute_pipeline(
+                device,
+                "spatial_upscaler_scale",
+                &shader_module,
+                "scale",
+                &scale_bind_group_layout,
+            ),
+            scale_bind_group_layout,
+            sharpen_pipeline: None,
+            scaled: ScratchTexture::new(
+                "spatial_upscaler_scaled",
+                TextureFormat::Rgba16Float,
+                TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            ),
+            device: device.clone(),
+        }
+    }
+
+    /// Encode commands to upscale and sharpen [`DlssRenderParameters::color`] into [`DlssRenderParameters::dlss_output`].
+    pub fn render(
+        &mut self,
+        render_parameters: DlssRenderParameters,
+        command_encoder: &mut CommandEncoder,
+    ) -> Result<(), DlssError> {
+        render_parameters.validate()?;
+
+        let output = &render_parameters.dlss_output;
+        let output_format = output.format();
+        if self
+            .sharpen_pipeline
+            .as_ref()
+            .is_none_or(|(format, ..)| *format != output_format)
+        {
+            self.sharpen_pipeline = Some(self.create_sharpen_pipeline(output_format)?);
+        }
+
+        let input_size = render_parameters
+            .partial_texture_size
+            .unwrap_or(self.render_resolution);
+        let output_size = output.size();
+
+        let scale = input_size != output_size;
+        if scale {
+            self.scaled.resize(output_size, &self.device);
+            let uniform_buffer = self.create_uniform_buffer(input_size);
+            let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+                label: Some("spatial_upscaler_scale"),
+                layout: &self.scale_bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(render_parameters.color.view),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::TextureView(self.scaled.texture().view),
+                    },
+                ],
+            });
+            dispatch(
+                command_encoder,
+                "spatial_upscaler_scale",
+                &self.scale_pipeline,
+                &bind_group,
+                output_size,
+            );
+        }
+        let (sharpen_input, sharpen_input_size) = if scale {
+            (self.scaled.texture(), output_size)
+        } else {
+            (render_parameters.color, input_size)
+        };
+
+        let uniform_buffer = self.create_uniform_buffer(sharpen_input_size);
+        let (_, bind_group_layout, pipeline) = self.sharpen_pipeline.as_ref().unwrap();
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("spatial_upscaler_sharpen"),
+            layout: bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(sharpen_input.view),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(output.view),
+                },
+            ],
+        });
+        dispatch(
+            command_encoder,
+            "spatial_upscaler_sharpen",
+            pipeline,
+            &bind_group,
+            output_size,
+        );
+
+        Ok(())
+    }
+
+    /// Sharpening strength, between 0.0 and 1.0. Defaults to 0.5.
+    pub fn set_sharpness(&mut self, sharpness: f32) {
+        self.sharpness = sharpness.clamp(0.0, 1.0);
+    }
+
+    pub fn sharpness(&self) -> f32 {
+        self.sharpness
+    }
+
+    /// The upscaled resolution the upscaler will output at.
+    pub fn upscaled_resolution(&self) -> UVec2 {
+        self.upscaled_resolution
+    }
+
+    /// The resolution the camera should render at, pre-upscaling.
+    pub fn render_resolution(&self) -> UVec2 {
+        self.render_resolution
+    }
+
+    /// Suggested mip bias for sampling textures.
+    ///
+    /// Unlike temporal upscalers, no extra negative bias is applied, as it would alias without temporal accumulation.
+    pub fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
+        (render_resolution.x as f32 / self.upscaled_resolution.x as f32).log2()
+    }
+
+    fn create_sharpen_pipeline(
+        &self,
+        output_format: TextureFormat,
+    ) -> Result<(TextureFormat, BindGroupLayout, ComputePipeline), DlssError> {
+        let source =
+            shader_source_with_output_format(include_str!("spatial_upscaler.wgsl"), output_format)
+                .ok_or_else(|| {
+                    DlssError::InvalidRenderParameters(format!(
+                        "dlss_output format {output_format:?} is not supported by SpatialUpscaler"
+                    ))
+                })?;
+        let shader_module = create_shader_module(&self.device, "spatial_upscaler_sharpen", &source);
+
+        let bind_group_layout = self
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("spatial_upscaler_sharpen"),
+                entries: &[
+                    uniform_entry(0),
+                    texture_entry(1, TextureSampleType::Float { filterable: false }),
+                    storage_texture_entry(3, output_format),
+                ],
+            });
+        let pipeline = create_compute_pipeline(
+            &self.device,
+            "spatial_upscaler_sharpen",
+            &shader_module,
+            "sharpen",
+            &bind_group_layout,
+        );
+
+        Ok((output_format, bind_group_layout, pipeline))
+    }
+
+    /// Uniforms shared by both passes, following NIS's `NVScalerUpdateConfig`.
+    fn create_uniform_buffer(&self, input_size: UVec2) -> Buffer {
+        let sharpen_slider = self.sharpness - 0.5;
+        let (min_scale, max_scale, limit_scale) = if sharpen_slider >= 0.0 {
+            (1.25, 1.25, 1.25)
+        } else {
+            (1.0, 1.75, 1.0)
+        };
+        let (detect_threshold, min_contrast_ratio, max_contrast_ratio): (f32, f32, f32) =
+            if self.hdr {
+                (32.0 / 1024.0, 1.5, 5.0)
+            } else {
+                (64.0 / 1024.0, 2.0, 10.0)
+            };
+        let sharp_start_y = 0.45f32;
+        let sharp_end_y = 0.9;
+        let sharp_strength_min = (0.4 + sharpen_slider * min_scale * 1.2f32).max(0.0);
+        let sharp_strength_max = 1.6 + sharpen_slider * max_scale * 1.8;
+        let sharp_limit_min = (0.14 + sharpen_slider * limit_scale * 0.32f32).max(0.1);
+        let sharp_limit_max = 0.5 + sharpen_slider * limit_scale * 0.6;
+
+        let uniforms = [
+            input_size.as_vec2().uniform_words(),
+            (2.0f32 * 1127.0 / 1024.0).uniform_words(),
+            detect_threshold.uniform_words(),
+            min_contrast_ratio.uniform_words(),
+            (1.0 / (max_contrast_ratio - min_contrast_ratio)).uniform_words(),
+            sharp_start_y.uniform_words(),
+            (1.0 / (sharp_end_y - sharp_start_y)).uniform_words(),
+            sharp_strength_min.uniform_words(),
+            (sharp_strength_max - sharp_strength_min).uniform_words(),
+            sharp_limit_min.uniform_words(),
+            (sharp_limit_max - sharp_limit_min).uniform_words(),
+            (self.hdr as u32).uniform_words(),
+            // Padding
+            0u32.uniform_words(),
+        ]
+        .concat();
+        create_uniform_buffer(&self.device, "spatial_upscaler", &uniforms)
+    }
+}
+
+impl<'a> Upscaler<'a> for SpatialUpscaler {
+    fn upscaled_resolution(&self) -> UVec2 {
+        SpatialUpscaler::upscaled_resolution(self)
+    }
+
+    fn render_resolution(&self) -> UVec2 {
+        SpatialUpscaler::render_resolution(self)
+    }
+
+    fn render_resolution_range(&self) -> RangeInclusive<UVec2> {
+        self.render_resolution..=self.render_resolution
+    }
+
+    /// Spatial upscalers do not use jitter.
+    fn suggested_jitter(&self, _frame_number: u32, _render_resolution: UVec2) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
+        SpatialUpscaler::suggested_mip_bias(self, render_resolution)
+    }
+
+    fn render(
+        &mut self,
+        render_parameters: DlssRenderParameters<'a>,
+        command_encoder: &mut CommandEncoder,
+        _adapter: &Adapter,
+    ) -> Result<(), DlssError> {
+        SpatialUpscaler::render(self, render_parameters, command_encoder)
+    }
+
+    /// Spatial upscalers have no history to reset.
+    fn reset(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        DlssExposure,
+        test_utils::{create_texture, device, read_texture, texture},
+    };
+    use wgpu::{CommandEncoderDescriptor, Queue, Texture, TextureView};
+
+    const UPSCALED_RESOLUTION: UVec2 = UVec2::new(16, 16);
+
+    /// Upscale `color`, which must be of the upscaler's render resolution, and read back the output.
+    fn render(
+        upscaler: &mut SpatialUpscaler,
+        color: &[[f32; 4]],
+        device: &Device,
+        queue: &Queue,
+    ) -> Vec<[f32; 4]> {
+        let render_resolution = upscaler.render_resolution();
+        let input = |format, data: &[f32]| {
+            create_texture(
+                render_resolution,
+                format,
+                TextureUsages::TEXTURE_BINDING,
+                Some(data),
+                device,
+                queue,
+            )
+        };
+        let pixel_count = render_resolution.element_product() as usize;
+        let color = input(TextureFormat::Rgba32Float, color.as_flattened());
+        let depth = input(TextureFormat::R32Float, &vec![0.5; pixel_count]);
+        let motion_vectors = input(TextureFormat::Rg32Float, &vec![0.0; pixel_count * 2]);
+        let output: (Texture, TextureView) = create_texture(
+            upscaler.upscaled_resolution(),
+            TextureFormat::Rgba32Float,
+            TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC,
+            None,
+            device,
+            queue,
+        );
+
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor::default());
+        upscaler
+            .render(
+                DlssRenderParameters::new(
+                    texture(&color),
+                    texture(&depth),
+                    texture(&motion_vectors),
+                    DlssExposure::Value(1.0),
+                    texture(&output),
+                ),
+                &mut command_encoder,
+            )
+            .expect("Failed to render SpatialUpscaler");
+        read_texture(&output.0, command_encoder, device, queue)
+    }
+
+    #[test]
+    #[ignore = "requires a wgpu adapter"]
+    fn uniform_color_is_preserved() {
+        let (device, queue) = device();
+
+        let mut upscaler = SpatialUpscaler::new(
+            UPSCALED_RESOLUTION,
+            DlssPerfQualityMode::Performance,
+            DlssFeatureFlags::empty(),
+            &device,
+        );
+        assert_eq!(upscaler.render_resolution(), UPSCALED_RESOLUTION / 2);
+        upscaler.set_sharpness(1.0);
+
+        let color = [0.25, 0.5, 0.75, 1.0];
+        let pixel_count = upscaler.render_resolution().element_product() as usize;
+        let output = render(&mut upscaler, &vec![color; pixel_count], &device, &queue);
+
+        assert_eq!(output.len(), UPSCALED_RESOLUTION.element_product() as usize);
+        for pixel in output {
+            for (actual, expected) in pixel.iter().zip(color) {
+                // The scaled image is stored as Rgba16Float
+                assert!(
+                    (actual - expected).abs() < 1e-2,
+                    "expected {color:?}, got {pixel:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "requires a wgpu adapter"]
+    fn step_edge_does_not_ring() {
+        let (device, queue) = device();
+
+        // DLAA only sharpens, so the output can be compared to the input pixel for pixel
+        let mut upscaler = SpatialUpscaler::new(
+            UPSCALED_RESOLUTION,
+            DlssPerfQualityMode::Dlaa,
+            DlssFeatureFlags::empty(),
+            &device,
+        );
+        assert_eq!(upscaler.render_resolution(), UPSCALED_RESOLUTION);
+        upscaler.set_sharpness(1.0);
+
+        let (dark, bright) = ([0.2, 0.2, 0.2, 1.0], [0.6, 0.6, 0.6, 1.0]);
+        let input = (0..UPSCALED_RESOLUTION.y)
+            .flat_map(|_| 0..UPSCALED_RESOLUTION.x)
+            .map(|x| {
+                if x < UPSCALED_RESOLUTION.x / 2 {
+                    dark
+                } else {
+                    bright
+                }
+            })
+            .collect::<Vec<_>>();
+        let output = render(&mut upscaler, &input, &device, &queue);
+
+        // Contrast differs on both sides of the edge, so sharpening is suppressed instead of overshooting
+        for (i, (actual, expected)) in output.iter().zip(&input).enumerate() {
+            for (a, e) in actual.iter().zip(expected) {
+                assert!(
+                    (a - e).abs() < 1e-5,
+                    "pixel {i}: expected {expected:?}, got {actual:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "requires a wgpu adapter"]
+    fn unsupported_output_format_is_rejected() {
+        let (device, queue) = device();
+
+        let mut upscaler = SpatialUpscaler::new(
+            UPSCALED_RESOLUTION,
+            DlssPerfQualityMode::Dlaa,
+            DlssFeatureFlags::empty(),
+            &device,
+        );
+        let input = |format| {
+            create_texture(
+                UPSCALED_RESOLUTION,
+                format,
+                TextureUsages::TEXTURE_BINDING,
+                None,
+                &device,
+                &queue,
+            )
+        };
+        let color = input(TextureFormat::Rgba32Float);
+        let depth = input(TextureFormat::R32Float);
+        let motion_vectors = input(TextureFormat::Rg32Float);
+        let output = create_texture(
+            UPSCALED_RESOLUTION,
+            TextureFormat::Rg32Float,
+            TextureUsages::STORAGE_BINDING,
+            None,
+            &device,
+            &queue,
+        );
+
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor::default());
+        let result = upscaler.render(
+            DlssRenderParameters::new(
+                texture(&color),
+                texture(&depth),
+                texture(&motion_vectors),
+                DlssExposure::Value(1.0),
+                texture(&output),
+            ),
+            &mut command_encoder,
+        );
+        assert!(matches!(result, Err(DlssError::InvalidRenderParameters(_))));
+    }
+}
@@ -0,0 +1,198 @@
+// Replaced at runtime to match the format of the output texture
+alias OutputTexture = texture_storage_2d<rgba16float, write>;
+
+const PI: f32 = 3.14159265;
+const EPSILON: f32 = 1.0 / 255.0;
+
+// Sharpening constants, derived from the sharpness setting the same way as NIS's NVScalerUpdateConfig
+struct Uniforms {
+    // Size of the region of the input texture to read
+    input_size: vec2<f32>,
+    detect_ratio: f32,
+    detect_threshold: f32,
+    min_contrast_ratio: f32,
+    ratio_norm: f32,
+    sharp_start_y: f32,
+    sharp_scale_y: f32,
+    sharp_strength_min: f32,
+    sharp_strength_scale: f32,
+    sharp_limit_min: f32,
+    sharp_limit_scale: f32,
+    hdr: u32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var input: texture_2d<f32>;
+@group(0) @binding(2) var scaled_output: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(3) var output: OutputTexture;
+
+fn max3(c: vec3<f32>) -> f32 {
+    return max(c.r, max(c.g, c.b));
+}
+
+fn load_input(pixel: vec2<i32>) -> vec3<f32> {
+    let clamped = clamp(pixel, vec2(0), vec2<i32>(uniforms.input_size) - 1);
+    let color = textureLoad(input, clamped, 0).rgb;
+    // HDR colors are sharpened in a reversibly tonemapped space, so that thresholds apply to perceived contrast
+    return select(color, color / (1.0 + max3(color)), uniforms.hdr != 0u);
+}
+
+fn to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(c, c / max(1.0 - max3(c), 1e-4), uniforms.hdr != 0u);
+}
+
+fn luma(c: vec3<f32>) -> f32 {
+    return dot(c, vec3(0.2126, 0.7152, 0.0722));
+}
+
+fn lanczos3(x: f32) -> f32 {
+    if abs(x) < 1e-5 {
+        return 1.0;
+    }
+    if abs(x) >= 3.0 {
+        return 0.0;
+    }
+    let px = PI * x;
+    return 3.0 * sin(px) * sin(px / 3.0) / (px * px);
+}
+
+// Separable 6x6 Lanczos filter, clamped to the nearest 2x2 texels to reduce ringing
+@compute @workgroup_size(8, 8, 1)
+fn scale(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let output_size = textureDimensions(scaled_output);
+    if any(global_id.xy >= output_size) {
+        return;
+    }
+
+    let position = (vec2<f32>(global_id.xy) + 0.5) * uniforms.input_size / vec2<f32>(output_size) - 0.5;
+    let base = vec2<i32>(floor(position));
+    let f = position - floor(position);
+
+    var weights_x: array<f32, 6>;
+    var weights_y: array<f32, 6>;
+    var sum_x = 0.0;
+    var sum_y = 0.0;
+    for (var i = 0; i < 6; i++) {
+        weights_x[i] = lanczos3(f32(i - 2) - f.x);
+        weights_y[i] = lanczos3(f32(i - 2) - f.y);
+        sum_x += weights_x[i];
+        sum_y += weights_y[i];
+    }
+
+    var color = vec3(0.0);
+    var nearest_min = vec3(1e20);
+    var nearest_max = vec3(-1e20);
+    for (var y = 0; y < 6; y++) {
+        for (var x = 0; x < 6; x++) {
+            let sample = load_input(base + vec2(x - 2, y - 2));
+            color += sample * (weights_x[x] * weights_y[y]);
+            if (x == 2 || x == 3) && (y == 2 || y == 3) {
+                nearest_min = min(nearest_min, sample);
+                nearest_max = max(nearest_max, sample);
+            }
+        }
+    }
+    color = clamp(color / (sum_x * sum_y), nearest_min, nearest_max);
+
+    textureStore(scaled_output, global_id.xy, vec4(to_linear(color), 1.0));
+}
+
+// Weights of the 0, 90, 45 and 135 degree directions, from the 3x3 block centered on p[2][2]
+fn edge_map(p: array<array<f32, 5>, 5>) -> vec4<f32> {
+    let g_0 = abs(p[1][1] + p[1][2] + p[1][3] - p[3][1] - p[3][2] - p[3][3]);
+    let g_45 = abs(p[2][1] + p[1][1] + p[1][2] - p[3][2] - p[3][3] - p[2][3]);
+    let g_90 = abs(p[1][1] + p[2][1] + p[3][1] - p[1][3] - p[2][3] - p[3][3]);
+    let g_135 = abs(p[2][1] + p[3][1] + p[3][2] - p[1][2] - p[1][3] - p[2][3]);
+
+    let g_0_90_max = max(g_0, g_90);
+    let g_0_90_min = min(g_0, g_90);
+    let g_45_135_max = max(g_45, g_135);
+    let g_45_135_min = min(g_45, g_135);
+
+    if g_0_90_max + g_45_135_max == 0.0 {
+        return vec4(0.0);
+    }
+
+    let e_0_90 = min(g_0_90_max / (g_0_90_max + g_45_135_max), 1.0);
+    let e_45_135 = 1.0 - e_0_90;
+
+    let c_0_90 = g_0_90_max > g_0_90_min * uniforms.detect_ratio
+        && g_0_90_max > uniforms.detect_threshold
+        && g_0_90_max > g_45_135_min;
+    let c_45_135 = g_45_135_max > g_45_135_min * uniforms.detect_ratio
+        && g_45_135_max > uniforms.detect_threshold
+        && g_45_135_max > g_0_90_min;
+    let c_g_0_90 = g_0_90_max == g_0;
+    let c_g_45_135 = g_45_135_max == g_45;
+
+    let f_e_0_90 = select(1.0, e_0_90, c_0_90 && c_45_135);
+    let f_e_45_135 = select(1.0, e_45_135, c_0_90 && c_45_135);
+
+    return vec4(
+        select(0.0, f_e_0_90, c_0_90 && c_g_0_90),
+        select(0.0, f_e_0_90, c_0_90 && !c_g_0_90),
+        select(0.0, f_e_45_135, c_45_135 && c_g_45_135),
+        select(0.0, f_e_45_135, c_45_135 && !c_g_45_135),
+    );
+}
+
+// Reduces sharpening where contrast differs between both sides of the center, to limit ringing
+fn luma_transient_factor(y: array<f32, 5>) -> f32 {
+    let a_contrast = max(max(y[0], y[1]), y[2]) - min(min(y[0], y[1]), y[2]);
+    let b_contrast = max(max(y[2], y[3]), y[4]) - min(min(y[2], y[3]), y[4]);
+    let contrast_ratio = max(a_contrast, b_contrast) / (min(a_contrast, b_contrast) + EPSILON);
+    return 1.0 - saturate((contrast_ratio - uniforms.min_contrast_ratio) * uniforms.ratio_norm);
+}
+
+fn unsharp_mask(y: array<f32, 5>, strength: f32, limit: f32) -> f32 {
+    let usm = (-0.6001 * y[1] + 1.2002 * y[2] - 0.6001 * y[3]) * strength;
+    return clamp(usm, -limit, limit) * luma_transient_factor(y);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn sharpen(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let output_size = textureDimensions(output);
+    if any(global_id.xy >= output_size) {
+        return;
+    }
+    let pixel = vec2<i32>(global_id.xy);
+
+    var p: array<array<f32, 5>, 5>;
+    for (var i = 0; i < 5; i++) {
+        for (var j = 0; j < 5; j++) {
+            p[i][j] = luma(load_input(pixel + vec2(j - 2, i - 2)));
+        }
+    }
+    let color = load_input(pixel);
+
+    // Sharpen less, and limit sharpening more, in bright areas
+    let scale_y = 1.0 - saturate((p[2][2] - uniforms.sharp_start_y) * uniforms.sharp_scale_y);
+    let strength = scale_y * uniforms.sharp_strength_scale + uniforms.sharp_strength_min;
+    let limit = (scale_y * uniforms.sharp_limit_scale + uniforms.sharp_limit_min) * p[2][2];
+
+    let usm_0 = array(p[0][2], p[1][2], p[2][2], p[3][2], p[4][2]);
+    let usm_90 = array(p[2][0], p[2][1], p[2][2], p[2][3], p[2][4]);
+    let usm_45 = array(
+        p[1][1],
+        mix(p[2][1], p[1][2], 0.5),
+        p[2][2],
+        mix(p[3][2], p[2][3], 0.5),
+        p[3][3],
+    );
+    let usm_135 = array(
+        p[3][1],
+        mix(p[3][2], p[2][1], 0.5),
+        p[2][2],
+        mix(p[2][3], p[1][2], 0.5),
+        p[1][3],
+    );
+    let directional_usm = vec4(
+        unsharp_mask(usm_0, strength, limit),
+        unsharp_mask(usm_90, strength, limit),
+        unsharp_mask(usm_45, strength, limit),
+        unsharp_mask(usm_135, strength, limit),
+    );
+
+    let sharpened = max(color + dot(directional_usm, edge_map(p)), vec3(0.0));
+    textureStore(output, global_id.xy, vec4(to_linear(sharpened), 1.0));
+}
@@ -0,0 +1,373 @@
+use crate::{
+    DlssContext, DlssDebugOptions, DlssError, DlssFeatureFlags, DlssPerfQualityMode,
+    DlssRenderParameters, DlssSdk, DlssTexture, DlssToneMapperType, MotionVectorConvention,
+};
+use glam::{UVec2, Vec2};
+use std::{
+    mem,
+    ops::{Range, RangeInclusive},
+    sync::{Arc, Mutex},
+};
+use wgpu::{
+    Adapter, CommandEncoder, Device, Queue, Texture, TextureAspect, TextureFormat, TextureView,
+    TextureViewDescriptor, TextureViewDimension,
+};
+
+/// Number of views (eyes) rendered by a [`DlssStereoContext`].
+const EYE_COUNT: usize = 2;
+
+/// Like [`DlssContext`], but for stereo rendering (e.g. VR).
+///
+/// Manages one DLSS feature per eye. Textures in [`DlssRenderParameters`] may be 2D array textures,
+/// in which case the first two array layers selected by the view are used for the left and right eye respectively.
+/// Textures with a single layer are shared by both eyes.
+pub struct DlssStereoContext {
+    eyes: [DlssContext; EYE_COUNT],
+    eye_views: Vec<EyeView>,
+    sdk: Arc<Mutex<DlssSdk>>,
+    queue: Queue,
+}
+
+impl DlssStereoContext {
+    /// Create a new [`DlssStereoContext`].
+    ///
+    /// This is an expensive operation. The resulting context should be cached, and only recreated when settings change.
+    pub fn new(
+        upscaled_resolution: UVec2,
+        perf_quality_mode: DlssPerfQualityMode,
+        feature_flags: DlssFeatureFlags,
+        sdk: Arc<Mutex<DlssSdk>>,
+        device: &Device,
+        queue: &Queue,
+    ) -> Result<Self, DlssError> {
+        Ok(Self {
+            eyes: create_eyes(
+                upscaled_resolution,
+                perf_quality_mode,
+                feature_flags,
+                &sdk,
+                device,
+                queue,
+            )?,
+            eye_views: Vec::new(),
+            sdk,
+            queue: queue.clone(),
+        })
+    }
+
+    /// Recreate the DLSS features of both eyes with new settings, keeping settings changed through setters.
+    ///
+    /// If creating either eye fails, both eyes are left unchanged. Temporal history is reset.
+    ///
+    /// See [`DlssContext::reconfigure`].
+    pub fn reconfigure(
+        &mut self,
+        upscaled_resolution: UVec2,
+        perf_quality_mode: DlssPerfQualityMode,
+        feature_flags: DlssFeatureFlags,
+        device: &Device,
+        queue: &Queue,
+    ) -> Result<(), DlssError> {
+        let mut eyes = create_eyes(
+            upscaled_resolution,
+            perf_quality_mode,
+            feature_flags,
+            &self.sdk,
+            device,
+            queue,
+        )?;
+        for (eye, old_eye) in eyes.iter_mut().zip(&mut self.eyes) {
+            eye.inherit_settings(old_eye);
+        }
+        self.eyes = eyes;
+        Ok(())
+    }
+
+    /// Reset temporal history of both eyes on the next call to [`Self::render`].
+    pub fn reset(&mut self) {
+        for eye in &mut self.eyes {
+            eye.reset();
+        }
+    }
+
+    /// Encode commands to render DLSS for both eyes.
+    ///
+    /// Settings such as [`DlssRenderParameters::jitter_offset`] and [`DlssRenderParameters::reset`] apply to both eyes.
+    ///
+    /// Both eyes' parameters are validated before any commands are encoded. Only a failure reported by NGX itself for the
+    /// right eye can leave the left eye's commands encoded, in which case `command_encoder` should be discarded.
+    ///
+    /// `command_encoder` must be submitted before the next call to [`Self::render`]. The per-eye texture views this
+    /// context creates are released once the work submitted before that call has completed on the GPU.
+    pub fn render(
+        &mut self,
+        render_parameters: DlssRenderParameters,
+        command_encoder: &mut CommandEncoder,
+        adapter: &Adapter,
+    ) -> Result<(), DlssError> {
+        let output_layers = render_parameters.dlss_output.selector().layers;
+        if output_layers.len() < EYE_COUNT {
+            return Err(DlssError::InvalidRenderParameters(format!(
+                "dlss_output view selects {} array layer(s), but stereo rendering requires {EYE_COUNT}",
+                output_layers.len()
+            )));
+        }
+
+        for eye_view in &mut self.eye_views {
+            eye_view.used = false;
+        }
+        for texture in render_parameters.textures() {
+            for eye in 0..EYE_COUNT {
+                if let Some(key) = EyeViewKey::new(texture, eye) {
+                    let eye_view = match self.eye_views.iter().position(|v| v.key == key) {
+                        Some(i) => &mut self.eye_views[i],
+                        None => {
+                            self.eye_views.push(EyeView::new(key));
+                            self.eye_views.last_mut().unwrap()
+                        }
+                    };
+                    eye_view.used = true;
+                }
+            }
+        }
+
+        let eye_parameters = |eye| {
+            render_parameters.map_textures(|texture| eye_texture(&self.eye_views, texture, eye))
+        };
+        let result = self
+            .eyes
+            .iter()
+            .enumerate()
+            .try_for_each(|(eye, context)| context.validate_render_parameters(&eye_parameters(eye)))
+            .and_then(|()| {
+                self.eyes
+                    .iter_mut()
+                    .enumerate()
+                    .try_for_each(|(eye, context)| {
+                        context.render(eye_parameters(eye), command_encoder, adapter)
+                    })
+            });
+
+        // NGX records the raw image views, which wgpu does not track, so views that are no longer used are only dropped
+        // once all work submitted so far, including the last frame that used them, has completed
+        let (eye_views, unused_eye_views) = mem::take(&mut self.eye_views)
+            .into_iter()
+            .partition(|eye_view| eye_view.used);
+        self.eye_views = eye_views;
+        drop_after_submitted_work(unused_eye_views, &self.queue);
+
+        result
+    }
+
+    /// Describe how [`DlssRenderParameters::motion_vectors`] are stored, for both eyes.
+    ///
+    /// See [`DlssContext::set_motion_vector_convention`].
+    pub fn set_motion_vector_convention(&mut self, convention: MotionVectorConvention) {
+        for eye in &mut self.eyes {
+            eye.set_motion_vector_convention(convention);
+        }
+    }
+
+    pub fn motion_vector_convention(&self) -> MotionVectorConvention {
+        self.eyes[0].motion_vector_convention()
+    }
+
+    /// Whether to dilate motion vectors before DLSS, for both eyes.
+    ///
+    /// See [`DlssContext::set_motion_vector_dilation`].
+    pub fn set_motion_vector_dilation(&mut self, enabled: bool) {
+        for eye in &mut self.eyes {
+            eye.set_motion_vector_dilation(enabled);
+        }
+    }
+
+    pub fn motion_vector_dilation(&self) -> bool {
+        self.eyes[0].motion_vector_dilation()
+    }
+
+    /// The tonemapper applied to DLSS's output, for both eyes.
+    ///
+    /// See [`DlssContext::set_tone_mapper_type`].
+    pub fn set_tone_mapper_type(&mut self, tone_mapper_type: DlssToneMapperType) {
+        for eye in &mut self.eyes {
+            eye.set_tone_mapper_type(tone_mapper_type);
+        }
+    }
+
+    pub fn tone_mapper_type(&self) -> DlssToneMapperType {
+        self.eyes[0].tone_mapper_type()
+    }
+
+    /// Strength of sharpening applied to DLSS's output, for both eyes.
+    ///
+    /// See [`DlssContext::set_sharpness`].
+    pub fn set_sharpness(&mut self, sharpness: Option<f32>) {
+        for eye in &mut self.eyes {
+            eye.set_sharpness(sharpness);
+        }
+    }
+
+    pub fn sharpness(&self) -> Option<f32> {
+        self.eyes[0].sharpness()
+    }
+
+    /// Settings for the development version of DLSS, for both eyes.
+    ///
+    /// See [`DlssContext::set_debug_options`].
+    pub fn set_debug_options(&mut self, debug_options: DlssDebugOptions) {
+        for eye in &mut self.eyes {
+            eye.set_debug_options(debug_options);
+        }
+    }
+
+    pub fn debug_options(&self) -> DlssDebugOptions {
+        self.eyes[0].debug_options()
+    }
+
+    /// Whether the next call to [`Self::render`] logs a warning if the feature flags contradict its textures.
+    ///
+    /// See [`DlssContext::set_feature_flag_check`].
+    pub fn set_feature_flag_check(&mut self, enabled: bool) {
+        for eye in &mut self.eyes {
+            eye.set_feature_flag_check(enabled);
+        }
+    }
+
+    pub fn feature_flag_check(&self) -> bool {
+        self.eyes[0].feature_flag_check()
+    }
+
+    /// Suggested subpixel camera jitter for a given frame, shared by both eyes.
+    pub fn suggested_jitter(&self, frame_number: u32, render_resolution: UVec2) -> Vec2 {
+        self.eyes[0].suggested_jitter(frame_number, render_resolution)
+    }
+
+    /// Suggested mip bias for sampling textures.
+    pub fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
+        self.eyes[0].suggested_mip_bias(render_resolution)
+    }
+
+    /// The upscaled resolution DLSS will output at, per eye.
+    pub fn upscaled_resolution(&self) -> UVec2 {
+        self.eyes[0].upscaled_resolution()
+    }
+
+    /// The resolution the camera should render at, per eye, pre-upscaling.
+    pub fn render_resolution(&self) -> UVec2 {
+        self.eyes[0].render_resolution()
+    }
+
+    /// Like [`Self::render_resolution`], but returns a range of values for use with dynamic resolution scaling.
+    pub fn render_resolution_range(&self) -> RangeInclusive<UVec2> {
+        self.eyes[0].render_resolution_range()
+    }
+}
+
+impl Drop for DlssStereoContext {
+    fn drop(&mut self) {
+        // The last frame may still be in flight, see Self::render
+        drop_after_submitted_work(mem::take(&mut self.eye_views), &self.queue);
+    }
+}
+
+fn drop_after_submitted_work(eye_views: Vec<EyeView>, queue: &Queue) {
+    if !eye_views.is_empty() {
+        queue.on_submitted_work_done(move || drop(eye_views));
+    }
+}
+
+fn create_eyes(
+    upscaled_resolution: UVec2,
+    perf_quality_mode: DlssPerfQualityMode,
+    feature_flags: DlssFeatureFlags,
+    sdk: &Arc<Mutex<DlssSdk>>,
+    device: &Device,
+    queue: &Queue,
+) -> Result<[DlssContext; EYE_COUNT], DlssError> {
+    let create_eye = || {
+        DlssContext::new(
+            upscaled_resolution,
+            perf_quality_mode,
+            feature_flags,
+            Arc::clone(sdk),
+            device,
+            queue,
+        )
+    };
+    Ok([create_eye()?, create_eye()?])
+}
+
+fn eye_texture<'a>(
+    eye_views: &'a [EyeView],
+    texture: &DlssTexture<'a>,
+    eye: usize,
+) -> DlssTexture<'a> {
+    match EyeViewKey::new(texture, eye) {
+        Some(key) => {
+            let eye_view = eye_views.iter().find(|v| v.key == key).unwrap();
+            // Created from the layer selected by the original view, so the descriptor is known to be valid
+            DlssTexture {
+                texture: texture.texture,
+                view: &eye_view.view,
+                view_descriptor: Some(&eye_view.descriptor),
+            }
+        }
+        None => *texture,
+    }
+}
+
+/// Identifies a single-layer view of one eye's array layer.
+#[derive(PartialEq, Eq)]
+struct EyeViewKey {
+    texture: Texture,
+    format: TextureFormat,
+    aspect: TextureAspect,
+    mips: Range<u32>,
+    layer: u32,
+}
+
+impl EyeViewKey {
+    /// Returns `None` if the texture has a single layer shared by both eyes.
+    fn new(texture: &DlssTexture, eye: usize) -> Option<Self> {
+        let selector = texture.selector();
+        (selector.layers.len() > 1).then(|| Self {
+            texture: texture.texture.clone(),
+            format: texture.format(),
+            aspect: texture.aspect(),
+            mips: selector.mips,
+            layer: selector.layers.start + eye as u32,
+        })
+    }
+}
+
+struct EyeView {
+    key: EyeViewKey,
+    descriptor: TextureViewDescriptor<'static>,
+    view: TextureView,
+    /// Whether the view is used by the current call to [`DlssStereoContext::render`].
+    used: bool,
+}
+
+impl EyeView {
+    fn new(key: EyeViewKey) -> Self {
+        let descriptor = TextureViewDescriptor {
+            label: Some("dlss_stereo_eye"),
+            format: Some(key.format),
+            dimension: Some(TextureViewDimension::D2),
+            usage: None,
+            aspect: key.aspect,
+            base_mip_level: key.mips.start,
+            mip_level_count: Some(key.mips.len() as u32),
+            base_array_layer: key.layer,
+            array_layer_count: Some(1),
+        };
+        let view = key.texture.create_view(&descriptor);
+
+        Self {
+            key,
+            descriptor,
+            view,
+            used: false,
+        }
+    }
+}
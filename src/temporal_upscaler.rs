@@ -0,0 +1,495 @@
+use crate::{
+    DlssDepthCopyPass, DlssError, DlssExposure, DlssFeatureFlags, DlssPerfQualityMode,
+    DlssRenderParameters, HaltonSequence, JitterSequence, MotionVectorConvention, Upscaler,
+    compute_pass::{
+        ScratchTexture, UniformValue, create_compute_pipeline, create_shader_module,
+        create_uniform_buffer, dispatch, sampler_entry, shader_source_with_output_format,
+        storage_texture_entry, texture_entry, uniform_entry,
+    },
+    context::ExposureTexture,
+    jitter::jitter_phase_count,
+};
+use glam::{UVec2, Vec2};
+use std::{mem, ops::RangeInclusive};
+use wgpu::{
+    Adapter, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindingResource, CommandEncoder, ComputePipeline, Device, FilterMode, Queue, Sampler,
+    SamplerDescriptor, TextureFormat, TextureSampleType, TextureUsages,
+};
+
+/// Temporal anti-aliasing upscaler implemented with wgpu compute shaders, for use when DLSS is not supported.
+///
+/// Runs on any wgpu backend, and takes the same [`DlssRenderParameters`] as [`crate::DlssContext`], see [`Upscaler`].
+/// Only [`DlssRenderParameters::color`], [`DlssRenderParameters::depth`], [`DlssRenderParameters::motion_vectors`],
+/// [`DlssRenderParameters::exposure`], [`DlssRenderParameters::dlss_output`] and the jitter, reset and motion vector
+/// settings are used. [`DlssExposure::Automatic`] is treated as an exposure of 1.0.
+/// The alpha channel of [`DlssRenderParameters::color`] is accumulated along with color.
+///
+/// [`DlssRenderParameters::dlss_output`] must use one of the `Rgba8Unorm`, `Rgba16Float` or `Rgba32Float` formats.
+pub struct TemporalUpscaler {
+    upscaled_resolution: UVec2,
+    render_resolution: UVec2,
+    feature_flags: DlssFeatureFlags,
+    motion_vector_convention: MotionVectorConvention,
+    reset_pending: bool,
+    /// Pipeline for the current output format.
+    pipeline: Option<(TextureFormat, BindGroupLayout, ComputePipeline)>,
+    history_sampler: Sampler,
+    history: [ScratchTexture; 2],
+    history_index: usize,
+    history_size: UVec2,
+    depth_copy: DlssDepthCopyPass,
+    exposure_texture: ExposureTexture,
+    device: Device,
+}
+
+impl TemporalUpscaler {
+    /// Create a new [`TemporalUpscaler`].
+    ///
+    /// The render resolution is chosen from `perf_quality_mode` using the same ratios as DLSS.
+    /// Of `feature_flags`, only [`DlssFeatureFlags::InvertedDepth`] and [`DlssFeatureFlags::LowResolutionMotionVectors`] are used.
+    pub fn new(
+        upscaled_resolution: UVec2,
+        perf_quality_mode: DlssPerfQualityMode,
+        feature_flags: DlssFeatureFlags,
+        device: &Device,
+        queue: &Queue,
+    ) -> Self {
+        let history = |label| {
+            ScratchTexture::new(
+                label,
+                TextureFormat::Rgba16Float,
+                TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            )
+        };
+
+        Self {
+            upscaled_resolution,
+            render_resolution: perf_quality_mode.render_resolution(upscaled_resolution),
+            feature_flags,
+            motion_vector_convention: MotionVectorConvention::default(),
+            reset_pending: true,
+            pipeline: None,
+            history_sampler: device.create_sampler(&SamplerDescriptor {
+                label: Some("temporal_upscaler_history"),
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                ..Default::default()
+            }),
+            history: [
+                history("temporal_upscaler_history_a"),
+                history("temporal_upscaler_history_b"),
+            ],
+            history_index: 0,
+            history_size: UVec2::ZERO,
+            depth_copy: DlssDepthCopyPass::new(device),
+            exposure_texture: ExposureTexture::new(queue),
+            device: device.clone(),
+        }
+    }
+
+    /// Encode commands to upscale [`DlssRenderParameters::color`] into [`DlssRenderParameters::dlss_output`].
+    pub fn render(
+        &mut self,
+        render_parameters: DlssRenderParameters,
+        command_encoder: &mut CommandEncoder,
+    ) -> Result<(), DlssError> {
+        render_parameters.validate()?;
+
+        let output = &render_parameters.dlss_output;
+        let output_format = output.format();
+        if self
+            .pipeline
+            .as_ref()
+            .is_none_or(|(format, ..)| *format != output_format)
+        {
+            self.pipeline = Some(self.create_pipeline(output_format)?);
+        }
+
+        let output_size = output.size();
+        let mut reset = render_parameters.reset || mem::take(&mut self.reset_pending);
+        if self.history_size != output_size {
+            for history in &mut self.history {
+                history.resize(output_size, &self.device);
+            }
+            self.history_size = output_size;
+            reset = true;
+        }
+
+        let exposure = match render_parameters.exposure {
+            DlssExposure::Automatic => DlssExposure::Value(1.0),
+            exposure => exposure,
+        };
+        self.exposure_texture.update(&exposure, &self.device);
+        let DlssExposure::Manual {
+            exposure: exposure_texture,
+            exposure_scale,
+            pre_exposure,
+        } = self.exposure_texture.resolve(exposure)
+        else {
+            unreachable!("exposure is always a texture after resolving")
+        };
+        let exposure_scale =
+            exposure_scale.unwrap_or(1.0) / pre_exposure.filter(|x| *x > 0.0).unwrap_or(1.0);
+
+        let depth = if render_parameters.depth.format().has_depth_aspect() {
+            self.depth_copy
+                .encode(&render_parameters.depth, command_encoder, &self.device)?
+        } else {
+            render_parameters.depth
+        };
+
+        let render_size = render_parameters
+            .partial_texture_size
+            .unwrap_or(self.render_resolution);
+        let motion_vector_resolution = if self
+            .feature_flags
+            .contains(DlssFeatureFlags::LowResolutionMotionVectors)
+        {
+            render_size
+        } else {
+            self.upscaled_resolution
+        };
+        let motion_vector_scale = render_parameters.motion_vector_scale.unwrap_or_else(|| {
+            self.motion_vector_convention
+                .scale(motion_vector_resolution)
+        });
+
+        let uniforms = [
+            render_size.as_vec2().uniform_words(),
+            render_parameters.jitter_offset.uniform_words(),
+            (motion_vector_scale / motion_vector_resolution.as_vec2()).uniform_words(),
+            motion_vector_resolution.as_vec2().uniform_words(),
+            exposure_scale.uniform_words(),
+            (self.feature_flags.contains(DlssFeatureFlags::InvertedDepth) as u32).uniform_words(),
+            (reset as u32).uniform_words(),
+            // Padding
+            0u32.uniform_words(),
+        ]
+        .concat();
+        let uniform_buffer = create_uniform_buffer(&self.device, "temporal_upscaler", &uniforms);
+
+        let previous_history = self.history[self.history_index].texture();
+        let next_history = self.history[1 - self.history_index].texture();
+        let (_, bind_group_layout, pipeline) = self.pipeline.as_ref().unwrap();
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("temporal_upscaler"),
+            layout: bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(render_parameters.color.view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(depth.view),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(render_parameters.motion_vectors.view),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::TextureView(exposure_texture.view),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::TextureView(previous_history.view),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: BindingResource::Sampler(&self.history_sampler),
+                },
+                BindGroupEntry {
+                    binding: 7,
+                    resource: BindingResource::TextureView(next_history.view),
+                },
+                BindGroupEntry {
+                    binding: 8,
+                    resource: BindingResource::TextureView(output.view),
+                },
+            ],
+        });
+
+        dispatch(
+            command_encoder,
+            "temporal_upscaler",
+            pipeline,
+            &bind_group,
+            output_size,
+        );
+        self.history_index = 1 - self.history_index;
+
+        Ok(())
+    }
+
+    /// Reset temporal history on the next call to [`Self::render`], like [`DlssRenderParameters::reset`].
+    pub fn reset(&mut self) {
+        self.reset_pending = true;
+    }
+
+    /// Suggested subpixel camera jitter for a given frame.
+    ///
+    /// Uses a Halton(2, 3) sequence. See [`crate::JitterGenerator`] for other sequences.
+    pub fn suggested_jitter(&self, frame_number: u32, render_resolution: UVec2) -> Vec2 {
+        let phase_count = jitter_phase_count(render_resolution, self.upscaled_resolution);
+        HaltonSequence::default().sample(frame_number % phase_count)
+    }
+
+    /// Suggested mip bias for sampling textures.
+    pub fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
+        (render_resolution.x as f32 / self.upscaled_resolution.x as f32).log2() - 1.0
+    }
+
+    /// The upscaled resolution the upscaler will output at.
+    pub fn upscaled_resolution(&self) -> UVec2 {
+        self.upscaled_resolution
+    }
+
+    /// The resolution the camera should render at, pre-upscaling.
+    pub fn render_resolution(&self) -> UVec2 {
+        self.render_resolution
+    }
+
+    /// See [`crate::DlssContext::set_motion_vector_convention`].
+    pub fn set_motion_vector_convention(&mut self, convention: MotionVectorConvention) {
+        self.motion_vector_convention = convention;
+    }
+
+    pub fn motion_vector_convention(&self) -> MotionVectorConvention {
+        self.motion_vector_convention
+    }
+
+    fn create_pipeline(
+        &self,
+        output_format: TextureFormat,
+    ) -> Result<(TextureFormat, BindGroupLayout, ComputePipeline), DlssError> {
+        let source =
+            shader_source_with_output_format(include_str!("temporal_upscaler.wgsl"), output_format)
+                .ok_or_else(|| {
+                    DlssError::InvalidRenderParameters(format!(
+                        "dlss_output format {output_format:?} is not supported by TemporalUpscaler"
+                    ))
+                })?;
+        let shader_module = create_shader_module(&self.device, "temporal_upscaler", &source);
+
+        let unfilterable = TextureSampleType::Float { filterable: false };
+        let bind_group_layout = self
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("temporal_upscaler"),
+                entries: &[
+                    uniform_entry(0),
+                    texture_entry(1, unfilterable),
+                    texture_entry(2, unfilterable),
+                    texture_entry(3, unfilterable),
+                    texture_entry(4, unfilterable),
+                    texture_entry(5, TextureSampleType::Float { filterable: true }),
+                    sampler_entry(6),
+                    storage_texture_entry(7, TextureFormat::Rgba16Float),
+                    storage_texture_entry(8, output_format),
+                ],
+            });
+        let pipeline = create_compute_pipeline(
+            &self.device,
+            "temporal_upscaler",
+            &shader_module,
+            "temporal_upscale",
+            &bind_group_layout,
+        );
+
+        Ok((output_format, bind_group_layout, pipeline))
+    }
+}
+
+impl<'a> Upscaler<'a> for TemporalUpscaler {
+    fn upscaled_resolution(&self) -> UVec2 {
+        TemporalUpscaler::upscaled_resolution(self)
+    }
+
+    fn render_resolution(&self) -> UVec2 {
+        TemporalUpscaler::render_resolution(self)
+    }
+
+    fn render_resolution_range(&self) -> RangeInclusive<UVec2> {
+        self.render_resolution..=self.render_resolution
+    }
+
+    fn suggested_jitter(&self, frame_number: u32, render_resolution: UVec2) -> Vec2 {
+        TemporalUpscaler::suggested_jitter(self, frame_number, render_resolution)
+    }
+
+    fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
+        TemporalUpscaler::suggested_mip_bias(self, render_resolution)
+    }
+
+    fn render(
+        &mut self,
+        render_parameters: DlssRenderParameters<'a>,
+        command_encoder: &mut CommandEncoder,
+        _adapter: &Adapter,
+    ) -> Result<(), DlssError> {
+        TemporalUpscaler::render(self, render_parameters, command_encoder)
+    }
+
+    fn reset(&mut self) {
+        TemporalUpscaler::reset(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_texture, device, read_texture, texture};
+    use wgpu::{CommandEncoderDescriptor, Queue, Texture, TextureView};
+
+    const UPSCALED_RESOLUTION: UVec2 = UVec2::new(16, 16);
+
+    struct Scene {
+        color: (Texture, TextureView),
+        depth: (Texture, TextureView),
+        motion_vectors: (Texture, TextureView),
+        output: (Texture, TextureView),
+    }
+
+    impl Scene {
+        /// A render resolution scene filled with `color`, with no motion.
+        fn new(
+            upscaler: &TemporalUpscaler,
+            color: [f32; 4],
+            device: &Device,
+            queue: &Queue,
+        ) -> Self {
+            let render_resolution = upscaler.render_resolution();
+            let pixel_count = render_resolution.element_product() as usize;
+            Self {
+                color: create_texture(
+                    render_resolution,
+                    TextureFormat::Rgba32Float,
+                    TextureUsages::TEXTURE_BINDING,
+                    Some(&color.repeat(pixel_count)),
+                    device,
+                    queue,
+                ),
+                depth: create_texture(
+                    render_resolution,
+                    TextureFormat::R32Float,
+                    TextureUsages::TEXTURE_BINDING,
+                    Some(&vec![0.5; pixel_count]),
+                    device,
+                    queue,
+                ),
+                motion_vectors: create_texture(
+                    render_resolution,
+                    TextureFormat::Rg32Float,
+                    TextureUsages::TEXTURE_BINDING,
+                    Some(&vec![0.0; pixel_count * 2]),
+                    device,
+                    queue,
+                ),
+                output: create_texture(
+                    upscaler.upscaled_resolution(),
+                    TextureFormat::Rgba32Float,
+                    TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC,
+                    None,
+                    device,
+                    queue,
+                ),
+            }
+        }
+
+        /// Render a frame and read back the output.
+        fn render(
+            &self,
+            upscaler: &mut TemporalUpscaler,
+            frame_number: u32,
+            reset: bool,
+            device: &Device,
+            queue: &Queue,
+        ) -> Vec<[f32; 4]> {
+            let mut render_parameters = DlssRenderParameters::new(
+                texture(&self.color),
+                texture(&self.depth),
+                texture(&self.motion_vectors),
+                DlssExposure::Value(1.0),
+                texture(&self.output),
+            );
+            render_parameters.reset = reset;
+            render_parameters.jitter_offset =
+                upscaler.suggested_jitter(frame_number, upscaler.render_resolution());
+
+            let mut command_encoder =
+                device.create_command_encoder(&CommandEncoderDescriptor::default());
+            upscaler
+                .render(render_parameters, &mut command_encoder)
+                .expect("Failed to render TemporalUpscaler");
+            read_texture(&self.output.0, command_encoder, device, queue)
+        }
+    }
+
+    #[test]
+    #[ignore = "requires a wgpu adapter"]
+    fn static_scene_converges_to_input() {
+        let (device, queue) = device();
+
+        let mut upscaler = TemporalUpscaler::new(
+            UPSCALED_RESOLUTION,
+            DlssPerfQualityMode::Performance,
+            DlssFeatureFlags::empty(),
+            &device,
+            &queue,
+        );
+        let color = [0.25, 0.5, 2.0, 0.75];
+        let scene = Scene::new(&upscaler, color, &device, &queue);
+
+        for frame_number in 0..16 {
+            let output = scene.render(&mut upscaler, frame_number, false, &device, &queue);
+            for pixel in output {
+                for (actual, expected) in pixel.iter().zip(color) {
+                    assert!(
+                        (actual - expected).abs() < 1e-2,
+                        "frame {frame_number}: expected {color:?}, got {pixel:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "requires a wgpu adapter"]
+    fn reset_discards_history() {
+        let (device, queue) = device();
+
+        let new_upscaler = || {
+            TemporalUpscaler::new(
+                UPSCALED_RESOLUTION,
+                DlssPerfQualityMode::Performance,
+                DlssFeatureFlags::empty(),
+                &device,
+                &queue,
+            )
+        };
+        let mut upscaler = new_upscaler();
+        let first_scene = Scene::new(&upscaler, [1.0, 0.0, 0.0, 1.0], &device, &queue);
+        let second_scene = Scene::new(&upscaler, [0.0, 0.5, 1.0, 0.5], &device, &queue);
+
+        for frame_number in 0..8 {
+            first_scene.render(&mut upscaler, frame_number, false, &device, &queue);
+        }
+        let expected = second_scene.render(&mut new_upscaler(), 8, false, &device, &queue);
+
+        let output = second_scene.render(&mut upscaler, 8, true, &device, &queue);
+        assert_eq!(output, expected);
+
+        for frame_number in 9..12 {
+            first_scene.render(&mut upscaler, frame_number, false, &device, &queue);
+        }
+        upscaler.reset();
+        let output = second_scene.render(&mut upscaler, 8, false, &device, &queue);
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,170 @@
+// Replaced at runtime to match the format of the output texture
+alias OutputTexture = texture_storage_2d<rgba16float, write>;
+
+// Blend factor given to a current frame sample that lands exactly on an output pixel
+const BLEND_FACTOR: f32 = 0.1;
+// Lower bound of the blend factor, so that history is always refreshed
+const MIN_BLEND_FACTOR: f32 = 0.03;
+// Size of the neighborhood color box history is clipped to, in standard deviations
+const VARIANCE_CLIP_GAMMA: f32 = 1.25;
+
+struct Uniforms {
+    render_size: vec2<f32>,
+    jitter: vec2<f32>,
+    // Converts motion vector texels to a UV offset from the current to the previous frame
+    motion_vector_scale: vec2<f32>,
+    motion_vector_resolution: vec2<f32>,
+    exposure_scale: f32,
+    reverse_z: u32,
+    reset: u32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var color: texture_2d<f32>;
+@group(0) @binding(2) var depth: texture_2d<f32>;
+@group(0) @binding(3) var motion_vectors: texture_2d<f32>;
+@group(0) @binding(4) var exposure: texture_2d<f32>;
+@group(0) @binding(5) var history: texture_2d<f32>;
+@group(0) @binding(6) var history_sampler: sampler;
+@group(0) @binding(7) var history_output: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(8) var output: OutputTexture;
+
+fn max3(c: vec3<f32>) -> f32 {
+    return max(c.r, max(c.g, c.b));
+}
+
+// Reversible tonemapper, which keeps bright samples from dominating the resolve
+fn tonemap(c: vec3<f32>) -> vec3<f32> {
+    return c / (1.0 + max3(c));
+}
+
+fn inverse_tonemap(c: vec3<f32>) -> vec3<f32> {
+    return c / max(1.0 - max3(c), 1e-4);
+}
+
+// Approximation of a Blackman-Harris window with a radius of one pixel
+fn reconstruction_weight(offset: vec2<f32>) -> f32 {
+    return exp(-2.29 * dot(offset, offset));
+}
+
+fn clamp_render_pixel(pixel: vec2<i32>) -> vec2<i32> {
+    return clamp(pixel, vec2(0), vec2<i32>(uniforms.render_size) - 1);
+}
+
+// Catmull-Rom filtering using 5 bilinear taps, see "Filmic SMAA" by Jorge Jimenez
+fn sample_history(uv: vec2<f32>) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(history));
+    let position = uv * size;
+    let center = floor(position - 0.5) + 0.5;
+    let f = position - center;
+    let f2 = f * f;
+    let f3 = f2 * f;
+
+    let w0 = -0.5 * f3 + f2 - 0.5 * f;
+    let w1 = 1.5 * f3 - 2.5 * f2 + 1.0;
+    let w2 = -1.5 * f3 + 2.0 * f2 + 0.5 * f;
+    let w3 = 0.5 * f3 - 0.5 * f2;
+    let w12 = w1 + w2;
+
+    let uv0 = (center - 1.0) / size;
+    let uv3 = (center + 2.0) / size;
+    let uv12 = (center + w2 / w12) / size;
+
+    var result = textureSampleLevel(history, history_sampler, vec2(uv12.x, uv0.y), 0.0) * (w12.x * w0.y);
+    result += textureSampleLevel(history, history_sampler, vec2(uv0.x, uv12.y), 0.0) * (w0.x * w12.y);
+    result += textureSampleLevel(history, history_sampler, uv12, 0.0) * (w12.x * w12.y);
+    result += textureSampleLevel(history, history_sampler, vec2(uv3.x, uv12.y), 0.0) * (w3.x * w12.y);
+    result += textureSampleLevel(history, history_sampler, vec2(uv12.x, uv3.y), 0.0) * (w12.x * w3.y);
+    let weight = w12.x * w0.y + w0.x * w12.y + w12.x * w12.y + w3.x * w12.y + w12.x * w3.y;
+
+    // Catmull-Rom can ring below zero
+    return max(result / weight, vec4(0.0));
+}
+
+// Move history towards the center of the box until it is inside
+fn clip_to_box(history: vec3<f32>, center: vec3<f32>, extent: vec3<f32>) -> vec3<f32> {
+    let offset = history - center;
+    let units = abs(offset / max(extent, vec3(1e-5)));
+    let max_unit = max3(units);
+    return select(history, center + offset / max_unit, max_unit > 1.0);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn temporal_upscale(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let output_size = textureDimensions(history_output);
+    if any(global_id.xy >= output_size) {
+        return;
+    }
+
+    let uv = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(output_size);
+    let exposure = textureLoad(exposure, vec2(0), 0).r * uniforms.exposure_scale;
+
+    // Render pixel N is sampled at N + 0.5 - jitter, so the nearest sample to this output pixel is:
+    let render_position = uv * uniforms.render_size + uniforms.jitter;
+    let center_pixel = vec2<i32>(floor(render_position));
+
+    var color_sum = vec3(0.0);
+    var alpha_sum = 0.0;
+    var weight_sum = 0.0;
+    var moment_1 = vec3(0.0);
+    var moment_2 = vec3(0.0);
+    var alpha_min = 1.0;
+    var alpha_max = 0.0;
+    var closest_pixel = clamp_render_pixel(center_pixel);
+    var closest_depth = textureLoad(depth, closest_pixel, 0).r;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let pixel = center_pixel + vec2(x, y);
+            let clamped_pixel = clamp_render_pixel(pixel);
+
+            let color_sample = textureLoad(color, clamped_pixel, 0);
+            let sample = tonemap(color_sample.rgb * exposure);
+            let alpha = saturate(color_sample.a);
+            let weight = reconstruction_weight(vec2<f32>(pixel) + 0.5 - render_position);
+            color_sum += sample * weight;
+            alpha_sum += alpha * weight;
+            weight_sum += weight;
+            alpha_min = min(alpha_min, alpha);
+            alpha_max = max(alpha_max, alpha);
+            moment_1 += sample;
+            moment_2 += sample * sample;
+
+            let depth = textureLoad(depth, clamped_pixel, 0).r;
+            let closer = select(depth < closest_depth, depth > closest_depth, uniforms.reverse_z != 0u);
+            if closer {
+                closest_depth = depth;
+                closest_pixel = clamped_pixel;
+            }
+        }
+    }
+    let current = color_sum / weight_sum;
+    let current_alpha = alpha_sum / weight_sum;
+    let mean = moment_1 / 9.0;
+    let standard_deviation = sqrt(max(moment_2 / 9.0 - mean * mean, vec3(0.0)));
+
+    // Motion of the closest surface in the neighborhood, so that edges of foreground objects keep their history
+    let motion_vector_pixel = vec2<i32>(
+        (vec2<f32>(closest_pixel) + 0.5) * uniforms.motion_vector_resolution / uniforms.render_size
+    );
+    let motion = textureLoad(motion_vectors, motion_vector_pixel, 0).xy * uniforms.motion_vector_scale;
+    let history_uv = uv + motion;
+
+    var result = current;
+    var result_alpha = current_alpha;
+    if uniforms.reset == 0u && all(history_uv >= vec2(0.0)) && all(history_uv <= vec2(1.0)) {
+        let history_sample = sample_history(history_uv);
+        let history_color = tonemap(history_sample.rgb * exposure);
+        let clipped_history = clip_to_box(history_color, mean, standard_deviation * VARIANCE_CLIP_GAMMA);
+        let clamped_history_alpha = clamp(history_sample.a, alpha_min, alpha_max);
+
+        // Trust the current frame less when its nearest sample is far from this output pixel
+        let confidence = reconstruction_weight(vec2<f32>(center_pixel) + 0.5 - render_position);
+        let blend_factor = max(BLEND_FACTOR * confidence, MIN_BLEND_FACTOR);
+        result = mix(clipped_history, current, blend_factor);
+        result_alpha = mix(clamped_history_alpha, current_alpha, blend_factor);
+    }
+
+    let linear = vec4(inverse_tonemap(result) / max(exposure, 1e-8), result_alpha);
+    textureStore(history_output, global_id.xy, linear);
+    textureStore(output, global_id.xy, linear);
+}
@@ -0,0 +1,138 @@
+//! Helpers for tests that render with wgpu.
+
+use crate::DlssTexture;
+use glam::UVec2;
+use std::{
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+use wgpu::{
+    BufferDescriptor, BufferUsages, CommandEncoder, Device, DeviceDescriptor, Extent3d, Instance,
+    InstanceDescriptor, MapMode, Origin3d, PollType, Queue, RequestAdapterOptions,
+    TexelCopyBufferInfo, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+};
+
+/// wgpu's native futures complete without being woken, so they can be polled to completion.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// Any available device, falling back to a software adapter.
+///
+/// Tests using this are ignored by default, as CI may have neither. Run them with `cargo test -- --ignored`.
+pub fn device() -> (Device, Queue) {
+    let instance = Instance::new(&InstanceDescriptor::from_env_or_default());
+    let adapter = block_on(instance.request_adapter(&RequestAdapterOptions::default()))
+        .or_else(|_| {
+            block_on(instance.request_adapter(&RequestAdapterOptions {
+                force_fallback_adapter: true,
+                ..Default::default()
+            }))
+        })
+        .expect("No wgpu adapter available");
+    block_on(adapter.request_device(&DeviceDescriptor::default()))
+        .expect("Failed to request wgpu device")
+}
+
+/// A 2D texture, filled with `data` if given, which must be tightly packed 32-bit floats.
+pub fn create_texture(
+    size: UVec2,
+    format: TextureFormat,
+    usage: TextureUsages,
+    data: Option<&[f32]>,
+    device: &Device,
+    queue: &Queue,
+) -> (Texture, TextureView) {
+    let size = Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: usage | TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    if let Some(data) = data {
+        let bytes = data
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>();
+        queue.write_texture(
+            texture.as_image_copy(),
+            &bytes,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes.len() as u32 / size.height),
+                rows_per_image: None,
+            },
+            size,
+        );
+    }
+    let view = texture.create_view(&Default::default());
+    (texture, view)
+}
+
+pub fn texture((texture, view): &(Texture, TextureView)) -> DlssTexture<'_> {
+    DlssTexture::new(texture, view)
+}
+
+/// Submit `command_encoder`, and read back `texture`, which must be an `Rgba32Float` texture with
+/// [`TextureUsages::COPY_SRC`].
+pub fn read_texture(
+    texture: &Texture,
+    mut command_encoder: CommandEncoder,
+    device: &Device,
+    queue: &Queue,
+) -> Vec<[f32; 4]> {
+    let size = texture.size();
+    let bytes_per_row = size.width * 16;
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: (bytes_per_row * size.height) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    command_encoder.copy_texture_to_buffer(
+        TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        size,
+    );
+    queue.submit([command_encoder.finish()]);
+
+    buffer
+        .slice(..)
+        .map_async(MapMode::Read, |result| result.unwrap());
+    device.poll(PollType::Wait).unwrap();
+    let bytes = buffer.slice(..).get_mapped_range();
+    bytes
+        .chunks_exact(16)
+        .map(|pixel| {
+            std::array::from_fn(|i| f32::from_le_bytes(pixel[i * 4..i * 4 + 4].try_into().unwrap()))
+        })
+        .collect()
+}
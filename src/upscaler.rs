@@ -0,0 +1,146 @@
+use crate::{
+    DlssContext, DlssError, DlssRayReconstructionContext, DlssRayReconstructionRenderParameters,
+    DlssRenderParameters, DlssStereoContext,
+};
+use glam::{UVec2, Vec2};
+use std::ops::RangeInclusive;
+use wgpu::{Adapter, CommandEncoder};
+
+/// Common interface for temporal upscalers, so that DLSS and fallback implementations can be used interchangeably.
+///
+/// `P` is the type of the inputs given to [`Self::render`]. Every implementation other than
+/// [`DlssRayReconstructionContext`] takes the same [`DlssRenderParameters`], so `dyn Upscaler<'a>` can hold any of them.
+pub trait Upscaler<'a, P = DlssRenderParameters<'a>> {
+    /// The upscaled resolution the upscaler will output at.
+    fn upscaled_resolution(&self) -> UVec2;
+
+    /// The resolution the camera should render at, pre-upscaling.
+    fn render_resolution(&self) -> UVec2;
+
+    /// Like [`Self::render_resolution`], but returns a range of values for use with dynamic resolution scaling.
+    fn render_resolution_range(&self) -> RangeInclusive<UVec2>;
+
+    /// Suggested subpixel camera jitter for a given frame.
+    fn suggested_jitter(&self, frame_number: u32, render_resolution: UVec2) -> Vec2;
+
+    /// Suggested mip bias for sampling textures.
+    fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32;
+
+    /// Encode commands to upscale [`DlssRenderParameters::color`] into [`DlssRenderParameters::dlss_output`].
+    fn render(
+        &mut self,
+        render_parameters: P,
+        command_encoder: &mut CommandEncoder,
+        adapter: &Adapter,
+    ) -> Result<(), DlssError>;
+
+    /// Reset temporal history on the next call to [`Self::render`], e.g. after a camera cut.
+    fn reset(&mut self);
+}
+
+impl<'a> Upscaler<'a> for DlssContext {
+    fn upscaled_resolution(&self) -> UVec2 {
+        DlssContext::upscaled_resolution(self)
+    }
+
+    fn render_resolution(&self) -> UVec2 {
+        DlssContext::render_resolution(self)
+    }
+
+    fn render_resolution_range(&self) -> RangeInclusive<UVec2> {
+        DlssContext::render_resolution_range(self)
+    }
+
+    fn suggested_jitter(&self, frame_number: u32, render_resolution: UVec2) -> Vec2 {
+        DlssContext::suggested_jitter(self, frame_number, render_resolution)
+    }
+
+    fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
+        DlssContext::suggested_mip_bias(self, render_resolution)
+    }
+
+    fn render(
+        &mut self,
+        render_parameters: DlssRenderParameters<'a>,
+        command_encoder: &mut CommandEncoder,
+        adapter: &Adapter,
+    ) -> Result<(), DlssError> {
+        DlssContext::render(self, render_parameters, command_encoder, adapter)
+    }
+
+    fn reset(&mut self) {
+        DlssContext::reset(self);
+    }
+}
+
+impl<'a> Upscaler<'a> for DlssStereoContext {
+    /// Per eye.
+    fn upscaled_resolution(&self) -> UVec2 {
+        DlssStereoContext::upscaled_resolution(self)
+    }
+
+    /// Per eye.
+    fn render_resolution(&self) -> UVec2 {
+        DlssStereoContext::render_resolution(self)
+    }
+
+    fn render_resolution_range(&self) -> RangeInclusive<UVec2> {
+        DlssStereoContext::render_resolution_range(self)
+    }
+
+    fn suggested_jitter(&self, frame_number: u32, render_resolution: UVec2) -> Vec2 {
+        DlssStereoContext::suggested_jitter(self, frame_number, render_resolution)
+    }
+
+    fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
+        DlssStereoContext::suggested_mip_bias(self, render_resolution)
+    }
+
+    fn render(
+        &mut self,
+        render_parameters: DlssRenderParameters<'a>,
+        command_encoder: &mut CommandEncoder,
+        adapter: &Adapter,
+    ) -> Result<(), DlssError> {
+        DlssStereoContext::render(self, render_parameters, command_encoder, adapter)
+    }
+
+    fn reset(&mut self) {
+        DlssStereoContext::reset(self);
+    }
+}
+
+impl<'a> Upscaler<'a, DlssRayReconstructionRenderParameters<'a>> for DlssRayReconstructionContext {
+    fn upscaled_resolution(&self) -> UVec2 {
+        DlssRayReconstructionContext::upscaled_resolution(self)
+    }
+
+    fn render_resolution(&self) -> UVec2 {
+        DlssRayReconstructionContext::render_resolution(self)
+    }
+
+    fn render_resolution_range(&self) -> RangeInclusive<UVec2> {
+        DlssRayReconstructionContext::render_resolution_range(self)
+    }
+
+    fn suggested_jitter(&self, frame_number: u32, render_resolution: UVec2) -> Vec2 {
+        DlssRayReconstructionContext::suggested_jitter(self, frame_number, render_resolution)
+    }
+
+    fn suggested_mip_bias(&self, render_resolution: UVec2) -> f32 {
+        DlssRayReconstructionContext::suggested_mip_bias(self, render_resolution)
+    }
+
+    fn render(
+        &mut self,
+        render_parameters: DlssRayReconstructionRenderParameters<'a>,
+        command_encoder: &mut CommandEncoder,
+        adapter: &Adapter,
+    ) -> Result<(), DlssError> {
+        DlssRayReconstructionContext::render(self, render_parameters, command_encoder, adapter)
+    }
+
+    fn reset(&mut self) {
+        DlssRayReconstructionContext::reset(self);
+    }
+}